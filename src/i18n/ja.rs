@@ -0,0 +1,322 @@
+//! Japanese message catalog, keyed by the English message.
+//!
+//! `{}` placeholders are filled in order, so each translation keeps the
+//! same number and order of placeholders as its key.
+
+pub(super) fn translate(message: &str) -> Option<&'static str> {
+    let translated = match message {
+        // Errors
+        "Bluetooth error: {}" => "Bluetoothエラー: {}",
+        "Command execution error: {}" => "コマンド実行エラー: {}",
+        "Configuration error: {}" => "設定エラー: {}",
+        "AutoStart error: {}" => "オートスタートエラー: {}",
+        "Adapter error: {}" => "アダプターエラー: {}",
+        "Timed out: {}" => "タイムアウト: {}",
+        "Incompatible model error" => "非対応のモデルです",
+        "This device is not an IQOS ILUMA model" => "このデバイスはIQOS ILUMAモデルではありません",
+        "This device is not an {} model" => "このデバイスは{}モデルではありません",
+        "Requires an {} model" => "{}モデルが必要です",
+        "Unknown command: {}" => "不明なコマンド: {}",
+        "Unknown argument: {}" => "不明な引数: {}",
+        "Error: {}" => "エラー: {}",
+        "{} did not finish within {}" => "{}が{}以内に完了しませんでした",
+        "No reply to '{}' within {}" => "'{}' への応答が{}以内にありませんでした",
+        "{} expects {} argument(s)" => "{}には{}個の引数が必要です",
+        "No Bluetooth adapter found" => "Bluetoothアダプターが見つかりません",
+        "Adapter '{}' not found. Available adapters: {}" => "アダプター '{}' が見つかりません。利用可能なアダプター: {}",
+        "Adapter event stream closed" => "アダプターのイベントストリームが閉じられました",
+        "A scan timeout is required to collect all devices" => "すべてのデバイスを収集するにはスキャンのタイムアウトが必要です",
+        "The device did not report a serial number (characteristic {} in service {})" => "デバイスがシリアル番号を返しませんでした (キャラクタリスティック {}、サービス {})",
+        "The battery characteristic {} was not found in service {}" => "バッテリーキャラクタリスティック {} がサービス {} に見つかりません",
+        "The SCP control characteristic {} was not found in service {}" => "SCPコントロールキャラクタリスティック {} がサービス {} に見つかりません",
+        "SCP Control characteristic is required" => "SCPコントロールキャラクタリスティックが必要です",
+        "No notifications received" => "通知を受信しませんでした",
+        "No notifications received for pause mode" => "ポーズモードの通知を受信しませんでした",
+        "No arguments provided" => "引数が指定されていません",
+        "Data too short for vibration settings" => "振動設定のデータが短すぎます",
+        "Failed to parse vibration settings" => "振動設定を解析できませんでした",
+        "Invalid header for vibration settings" => "振動設定のヘッダーが不正です",
+        "Failed to parse brightness settings" => "明るさ設定を解析できませんでした",
+        "Invalid brightness level" => "明るさレベルが不正です",
+        "Invalid brightness level data" => "明るさレベルのデータが不正です",
+        "Invalid brightness level header" => "明るさレベルのヘッダーが不正です",
+        "Unknown brightness level" => "不明な明るさレベルです",
+        "Failed to parse flexpuff settings" => "Flexpuff設定を解析できませんでした",
+        "Invalid Flexpuff data" => "Flexpuffのデータが不正です",
+        "Invalid Flexpuff header" => "Flexpuffのヘッダーが不正です",
+        "Invalid argument for Flexpuff" => "Flexpuffの引数が不正です",
+        "Unknown Flexpuff state" => "不明なFlexpuffの状態です",
+        "Invalid flexbattery data received" => "不正なFlexbatteryデータを受信しました",
+        "Invalid flexbattery mode data" => "Flexbatteryモードのデータが不正です",
+        "Invalid flexbattery mode header" => "Flexbatteryモードのヘッダーが不正です",
+        "Unknown flexbattery mode" => "不明なFlexbatteryモードです",
+        "Invalid pause mode data received" => "不正なポーズモードデータを受信しました",
+        "Invalid pause mode data" => "ポーズモードのデータが不正です",
+        "Invalid pause mode header" => "ポーズモードのヘッダーが不正です",
+        "Unknown pause mode" => "不明なポーズモードです",
+        "'on' or 'off' must follow the pausemode argument" => "pausemode の後には 'on' または 'off' を指定してください",
+        "The pausemode value must be either 'on' or 'off'" => "pausemode の値は 'on' または 'off' のいずれかです",
+        "The charge option is only available for ILUMA models" => "charge オプションはILUMAモデルでのみ利用できます",
+        "FlexPuff is only available on ILUMA devices." => "FlexPuffはILUMAデバイスでのみ利用できます。",
+        "Invalid option: {}. Please specify 'enable' or 'disable'" => "不正なオプション: {}。'enable' または 'disable' を指定してください",
+        "Invalid option: {}. {}" => "不正なオプション: {}。{}",
+        "serial number {} does not match" => "シリアル番号 {} が一致しません",
+        "{} was not found" => "{}が見つかりませんでした",
+
+        // Settings
+        "Brightness Level: {}" => "明るさレベル: {}",
+        "high" => "高",
+        "low" => "低",
+        "Flexbattery: {}" => "Flexbattery: {}",
+        "Pause mode: {}" => "ポーズモード: {}",
+        "Eco" => "エコ",
+        "Performance" => "パフォーマンス",
+        "On" => "オン",
+        "Off" => "オフ",
+        "on" => "オン",
+        "off" => "オフ",
+        "Flexpuff is enabled" => "Flexpuffは有効です",
+        "Flexpuff is disabled" => "Flexpuffは無効です",
+        "Vibration Settings" => "振動設定",
+        "when charge start: {}" => "充電開始時: {}",
+        "when heating: {}" => "加熱開始時: {}",
+        "when starting: {}" => "使用開始時: {}",
+        "when puff end soon: {}" => "パフ終了間近: {}",
+        "when terminated: {}" => "終了時: {}",
+        "Model: {}\nModel Number: {}\nSerial Number: {}\nManufacturer Name: {}\n\nStick:\n\tProduct Number: {}\n\tSoftware Revision: {}\nHolder:\n\tHolder Product Number: {}" => "モデル: {}\nモデル番号: {}\nシリアル番号: {}\n製造元: {}\n\nスティック:\n\t製品番号: {}\n\tソフトウェアリビジョン: {}\nホルダー:\n\tホルダー製品番号: {}",
+        "Model: {}\nModel Number: {}\nSerial Number: {}\nSoftware Revision: {}\nManufacturer Name: {}\nProduct Number: {}" => "モデル: {}\nモデル番号: {}\nシリアル番号: {}\nソフトウェアリビジョン: {}\n製造元: {}\n製品番号: {}",
+
+        // Startup and connection
+        "Bluetooth adapter is on" => "Bluetoothアダプターがオンになりました",
+        "Bluetooth adapter is powered off. Waiting for it to be turned on (Ctrl-C to quit)..." => "Bluetoothアダプターの電源がオフです。オンになるのを待っています (Ctrl-Cで終了)...",
+        "Checking {} ({})..." => "{} ({}) を確認しています...",
+        "Connecting to {} ({})..." => "{} ({}) に接続しています...",
+        "Connected!" => "接続しました!",
+        "Could not connect to the preferred device: {}" => "優先デバイスに接続できませんでした: {}",
+        "Waiting for devices..." => "デバイスを待っています...",
+        "Scanning for IQOS devices (↑/↓ or number to select, Enter to connect, q to quit)" => "IQOSデバイスをスキャンしています (↑/↓または番号で選択、Enterで接続、qで終了)",
+        " (known)" => " (登録済み)",
+        " (preferred)" => " (優先)",
+        " (not supported by this device)" => " (このデバイスでは非対応)",
+        "No device selected" => "デバイスが選択されていません",
+        "No device specified. Use --device or set a preferred device with 'device prefer'" => "デバイスが指定されていません。--device を使うか 'device prefer' で優先デバイスを設定してください",
+        "Unknown device '{}'. Use 'device list' in the console to see known devices" => "不明なデバイス '{}'。登録済みデバイスはコンソールの 'device list' で確認できます",
+
+        // Daemon
+        "Connected to {}" => "{} に接続しました",
+        "Could not connect to {}: {}" => "{} に接続できませんでした: {}",
+        "Listening on {} (Ctrl-C to stop)" => "{} で待ち受けています (Ctrl-Cで停止)",
+        "A daemon is already listening on {}" => "{} ではすでにデーモンが待ち受けています",
+        "Could not listen on {}" => "{} で待ち受けできませんでした",
+        "Lost the connection to the daemon: {}" => "デーモンとの接続が切れました: {}",
+        "Only JSON-RPC 2.0 is supported" => "JSON-RPC 2.0 のみ対応しています",
+        "No device specified. Pass \"device\" with an alias or serial number" => "デバイスが指定されていません。\"device\" に別名またはシリアル番号を指定してください",
+        "Unknown method: {}" => "不明なメソッド: {}",
+        "Missing parameter: {}" => "パラメーターがありません: {}",
+        "Invalid parameter {}: {}" => "パラメーター {} が正しくありません: {}",
+        "Listening on http://{} (Ctrl-C to stop)" => "http://{} で待ち受けています (Ctrl-Cで停止)",
+        "Usage: serve --http <ADDR> [device...]" => "使い方: serve --http <アドレス> [デバイス...]",
+        "Invalid JSON body: {}" => "JSON本文が正しくありません: {}",
+        "Missing field: {}" => "フィールドがありません: {}",
+        "Invalid field {}: {}" => "フィールド {} が正しくありません: {}",
+        "Unknown resource: {}" => "不明なリソース: {}",
+        "{} cannot be read from the device, only set with PUT" => "{} はデバイスから読み取れません。PUT で設定のみ可能です",
+        "{} can only be read" => "{} は読み取り専用です",
+
+        // Console
+        "IQOS Command Console v{}" => "IQOS コマンドコンソール v{}",
+        "Type 'help' to display available commands, 'exit' to quit" => "'help' で利用可能なコマンドを表示、'exit' で終了します",
+        "Type 'help <command>' for usage, or add --json to a command for JSON output" => "'help <コマンド>' で使い方を表示します。コマンドに --json を付けるとJSONで出力します",
+        "Available commands for {}:" => "{} で利用可能なコマンド:",
+        "disconnected" => "切断",
+        "Dry run is on: frames are shown, not sent" => "ドライランが有効です: フレームは送信されず表示のみ行われます",
+        "Dry run is off" => "ドライランは無効です",
+        "[dry-run] {}: {}" => "[ドライラン] {}: {}",
+        "[dry-run] {}: {}, answered with {}" => "[ドライラン] {}: {}、応答 {}",
+
+        // Frame descriptions in dry runs
+        "confirm the previous command" => "直前のコマンドを確定",
+        "start vibrating" => "振動を開始",
+        "stop vibrating" => "振動を停止",
+        "lock the device" => "デバイスをロック",
+        "unlock the device" => "デバイスのロックを解除",
+        "apply the lock state" => "ロック状態を反映",
+        "set brightness to high" => "明るさを高に設定",
+        "set holder brightness to high" => "ホルダーの明るさを高に設定",
+        "set brightness to low" => "明るさを低に設定",
+        "set holder brightness to low" => "ホルダーの明るさを低に設定",
+        "read brightness" => "明るさを読み取り",
+        "read vibration settings" => "振動設定を読み取り",
+        "read vibration when charging starts" => "充電開始時の振動設定を読み取り",
+        "read vibration settings of the holder" => "ホルダーの振動設定を読み取り",
+        "apply the charging vibration setting" => "充電時の振動設定を反映",
+        "enable Smart Gesture" => "スマートジェスチャーを有効化",
+        "disable Smart Gesture" => "スマートジェスチャーを無効化",
+        "enable autostart" => "オートスタートを有効化",
+        "disable autostart" => "オートスタートを無効化",
+        "read FlexPuff" => "FlexPuffを読み取り",
+        "enable FlexPuff" => "FlexPuffを有効化",
+        "disable FlexPuff" => "FlexPuffを無効化",
+        "read FlexBattery mode" => "FlexBatteryモードを読み取り",
+        "read pause mode" => "ポーズモードを読み取り",
+        "set FlexBattery to eco" => "FlexBatteryをエコに設定",
+        "set FlexBattery to performance" => "FlexBatteryをパフォーマンスに設定",
+        "enable pause mode" => "ポーズモードを有効化",
+        "disable pause mode" => "ポーズモードを無効化",
+        "read the product number" => "製品番号を読み取り",
+        "read the holder product number" => "ホルダーの製品番号を読み取り",
+        "unknown frame" => "不明なフレーム",
+        "vibrate when charging starts: {}" => "充電開始時の振動: {}",
+        "set vibration: heating {}, starting {}, puff end {}, terminated {}" => "振動を設定: 加熱開始 {}、使用開始 {}、パフ終了間近 {}、終了時 {}",
+        "No reply is known for this request in a dry run" => "ドライランではこの要求への応答がわかりません",
+        "Device disconnected" => "デバイスが切断されました",
+        "Battery {}%" => "バッテリー {}%",
+        "Notification: {}" => "通知: {}",
+        "Notifications: {}" => "通知レベル: {}",
+        "Notifications set to {}" => "通知レベルを {} に設定しました",
+        "Goodbye!" => "さようなら!",
+        "No matching commands in the history" => "履歴に一致するコマンドはありません",
+        "Could not save history to {}: {}" => "履歴を {} に保存できませんでした: {}",
+        "Output format: {}" => "出力形式: {}",
+        "Output format set to {}" => "出力形式を {} に設定しました",
+        "Unknown output format: {}" => "不明な出力形式です: {}",
+        "Unknown notification level: {}" => "不明な通知レベルです: {}",
+        "{} [y/N] " => "{} [y(はい)/N(いいえ)] ",
+        "Could not read {}" => "{} を読み込めませんでした",
+        "Ignoring invalid {}: {}" => "不正な {} を無視します: {}",
+        "Ignoring alias '{}': a command with this name exists" => "エイリアス '{}' を無視します: 同じ名前のコマンドがあります",
+        "Ignoring alias '{}': it refers to itself" => "エイリアス '{}' を無視します: 自分自身を参照しています",
+        "Alias for: {}" => "エイリアス: {}",
+        "Usage: {}" => "使い方: {}",
+
+        "Every {}: {} at {} (Ctrl-C to stop)" => "{}ごと: {} {} (Ctrl-Cで停止)",
+
+        // Scripts
+        "Scripts cannot source other scripts" => "スクリプトから他のスクリプトは実行できません",
+        "Expectation failed: {} {} {} ({} is {})" => "期待値と一致しません: {} {} {} ({} は {})",
+        "{} line(s) of {} failed" => "{} 行が失敗しました ({})",
+        "Usage: sleep <duration>, e.g. sleep 2s" => "使い方: sleep <時間>。例: sleep 2s",
+        "Usage: expect battery <op> <percent> | expect model == <model>" => "使い方: expect battery <演算子> <パーセント> | expect model == <モデル>",
+
+        // Command results
+        "Battery status: {}%" => "バッテリー残量: {}%",
+        "Locked the IQOS" => "IQOSをロックしました",
+        "Unlocked the IQOS" => "IQOSのロックを解除しました",
+        "Find My IQOS..." => "IQOSを探す...",
+        "Press <Enter> to stop vibration" => "<Enter>を押すと振動を停止します",
+        "Vibration stopped." => "振動を停止しました。",
+        "Vibration settings updated" => "振動設定を更新しました",
+        "Set brightness to {}" => "明るさを {} に設定しました",
+        "Flexbattery mode updated." => "Flexbatteryモードを更新しました。",
+        "Flexpuff enabled" => "Flexpuffを有効にしました",
+        "Flexpuff disabled" => "Flexpuffを無効にしました",
+        "Flexpuff status: {}" => "Flexpuffの状態: {}",
+        "Smart Gesture enabled" => "スマートジェスチャーを有効にしました",
+        "Smart Gesture disabled" => "スマートジェスチャーを無効にしました",
+        "Autostart enabled" => "オートスタートを有効にしました",
+        "Autostart disabled" => "オートスタートを無効にしました",
+        "No known devices" => "登録済みのデバイスはありません",
+        "Device {} is not registered" => "デバイス {} は登録されていません",
+        "Alias '{}' is already used by another device" => "エイリアス '{}' は他のデバイスで使われています",
+        "This device is now known as '{}'" => "このデバイスを '{}' として登録しました",
+        "Alias removed" => "エイリアスを削除しました",
+        "This device will be connected automatically at startup" => "起動時にこのデバイスへ自動接続します",
+        "No device will be connected automatically at startup" => "起動時の自動接続を解除しました",
+        "This device has been removed from the known devices" => "このデバイスを登録済みデバイスから削除しました",
+        "Lock the IQOS? It cannot be used until it is unlocked" => "IQOS をロックしますか？ ロックを解除するまで使用できません",
+        "Forget this device? Its alias and preference are removed" => "このデバイスを削除しますか？ 別名と自動接続の設定も削除されます",
+        "Cancelled" => "キャンセルしました",
+        "Could not save known devices: {}" => "登録済みデバイスを保存できませんでした: {}",
+        "Invalid log file: {}" => "ログファイルの指定が正しくありません: {}",
+        "Could not open log file {}" => "ログファイル {} を開けませんでした",
+
+        // Command descriptions
+        "Display available commands, or the usage of one" => "利用可能なコマンド、またはコマンドの使い方を表示します",
+        "Display battery status" => "バッテリー残量を表示します",
+        "Display device status" => "デバイスの状態を表示します",
+        "Lock the device" => "デバイスをロックします",
+        "Unlock the device" => "デバイスのロックを解除します",
+        "Show or change the output format (or add --json to a command)" => "出力形式を表示または変更します (コマンドに --json を付けることもできます)",
+        "Vibrate the device until Enter is pressed" => "Enterを押すまでデバイスを振動させます",
+        "Run the commands in a script file" => "スクリプトファイルのコマンドを実行します",
+        "List previous commands, or those containing a text" => "これまでのコマンド、または指定した文字列を含むコマンドを一覧表示します",
+        "Exit the program (also: quit)" => "プログラムを終了します (quit でも可)",
+        "Configure autostart feature" => "オートスタート機能を設定します",
+        "Configure device brightness level" => "デバイスの明るさを設定します",
+        "Configure FlexBattery feature" => "FlexBattery機能を設定します",
+        "Configure FlexPuff feature" => "FlexPuff機能を設定します",
+        "Configure Smart Gesture feature" => "スマートジェスチャー機能を設定します",
+        "Configure device vibration settings" => "デバイスの振動を設定します",
+        "Manage known devices" => "登録済みデバイスを管理します",
+        "Show frames instead of sending them to the device" => "デバイスに送信せずにフレームを表示します",
+        "Show or change which device events are printed while idle" => "待機中に表示するデバイスのイベントを表示または変更します",
+        "Re-run a status command at an interval, highlighting changes" => "状態を表示するコマンドを一定間隔で再実行し、変化を強調表示します",
+        "Open the full-screen dashboard" => "全画面のダッシュボードを開きます",
+        "Keep devices connected and serve JSON-RPC on a Unix socket" => "デバイスへの接続を保ち、Unixソケットで JSON-RPC を提供します",
+        "Serve device settings as JSON over HTTP" => "デバイスの設定を HTTP で JSON として提供します",
+        "Print a shell completion script" => "シェルの補完スクリプトを出力します",
+        "Print the result as JSON" => "結果をJSONで出力します",
+
+        // Command usages
+        "Usage: help [command]" => "使い方: help [コマンド]",
+        "Usage: battery" => "使い方: battery",
+        "Usage: info" => "使い方: info",
+        "Usage: lock" => "使い方: lock",
+        "Usage: unlock" => "使い方: unlock",
+        "Usage: format [text|json]" => "使い方: format [text|json]",
+        "Usage: findmyiqos" => "使い方: findmyiqos",
+        "Usage: exit" => "使い方: exit",
+        "Usage: source <file> [--continue-on-error]" => "使い方: source <ファイル> [--continue-on-error]",
+        "Usage: history [text]" => "使い方: history [文字列]",
+        "Usage: completions <bash|zsh|fish>" => "使い方: completions <bash|zsh|fish>",
+        "Usage: watch <interval> <battery|vibration|flexbattery>\nExample: watch 30s battery (Ctrl-C to stop)" => "使い方: watch <間隔> <battery|vibration|flexbattery>\n例: watch 30s battery (Ctrl-Cで停止)",
+        "Usage: notify [off|normal|verbose]" => "使い方: notify [off|normal|verbose]",
+        "Usage: dryrun [on|off]" => "使い方: dryrun [on|off]",
+        "Usage: autostart [on|off]" => "使い方: autostart [on|off]",
+        "Usage: brightness [high|low]" => "使い方: brightness [high|low]",
+        "Usage: flexbattery [performance|eco] [pausemode on|off]" => "使い方: flexbattery [performance|eco] [pausemode on|off]",
+        "Usage: flexpuff [status|enable|disable]" => "使い方: flexpuff [status|enable|disable]",
+        "Usage: smartgesture [enable|disable]" => "使い方: smartgesture [enable|disable]",
+        "Usage: device [list|name <alias>|unname|prefer|unprefer|forget]" => "使い方: device [list|name <エイリアス>|unname|prefer|unprefer|forget]",
+        "Usage: device name <alias>" => "使い方: device name <エイリアス>",
+        "Usage: vibration [charge|heating|starting|terminated|puffend] [on|off] ...\nExample: vibration charge on heating on puffend on\nNote: charge option is only available for ILUMA models" => "使い方: vibration [charge|heating|starting|terminated|puffend] [on|off] ...\n例: vibration charge on heating on puffend on\n注意: charge オプションはILUMAモデルでのみ利用できます",
+
+        // Dashboard
+        "Device" => "デバイス",
+        "Holder battery" => "ホルダーのバッテリー",
+        "Settings" => "設定",
+        "Events" => "イベント",
+        "Model" => "モデル",
+        "Serial number" => "シリアル番号",
+        "Model number" => "モデル番号",
+        "Product number" => "製品番号",
+        "Software" => "ソフトウェア",
+        "Holder product" => "ホルダー製品",
+        "Battery" => "バッテリー",
+        "Vibration" => "振動",
+        "Brightness" => "明るさ",
+        "Vibrate when charge starts" => "充電開始時に振動",
+        "Vibrate when heating starts" => "加熱開始時に振動",
+        "Vibrate when ready to use" => "使用可能時に振動",
+        "Vibrate when puffs end soon" => "パフ終了間近に振動",
+        "Vibrate when terminated" => "終了時に振動",
+        "FlexPuff" => "FlexPuff",
+        "FlexBattery" => "FlexBattery",
+        "Pause mode" => "ポーズモード",
+        "Autostart" => "オートスタート",
+        "Smart Gesture" => "スマートジェスチャー",
+        "Battery {}% -> {}%" => "バッテリー {}% -> {}%",
+        "Charger battery {}% -> {}%" => "充電器のバッテリー {}% -> {}%",
+        "Charger battery" => "充電器のバッテリー",
+        "not reported" => "取得できません",
+        "Vibration settings could not be loaded; press r to reload them" => "バイブレーション設定を読み込めませんでした。r で再読み込みしてください",
+        "Settings loaded" => "設定を読み込みました",
+        "{} set to {}" => "{} を {} に設定しました",
+        "Pause mode is only available in performance mode" => "ポーズモードはパフォーマンスモードでのみ利用できます",
+        "n/a (eco)" => "なし (エコ)",
+        "  (ILUMA settings hidden for this model)" => "  (このモデルではILUMAの設定は表示されません)",
+        "↑/↓ select  Enter/Space toggle  r reload  q quit" => "↑/↓ 選択  Enter/Space 切替  r 再読込  q 終了",
+        _ => return None,
+    };
+    Some(translated)
+}
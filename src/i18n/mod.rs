@@ -0,0 +1,84 @@
+//! Message catalog for console output and error messages.
+//!
+//! Messages are looked up by their English text, so untranslated messages
+//! fall back to English. Use `tr!` for literals, with `{}` placeholders
+//! filled in order, and `translate` for messages built at runtime.
+
+mod ja;
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    En,
+    Ja,
+}
+
+impl Lang {
+    /// Pick the language from `LC_ALL`, `LC_MESSAGES` or `LANG`
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if locale.starts_with("ja") {
+            Lang::Ja
+        } else {
+            Lang::En
+        }
+    }
+}
+
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+pub fn lang() -> Lang {
+    if LANG.load(Ordering::Relaxed) == Lang::Ja as u8 {
+        Lang::Ja
+    } else {
+        Lang::En
+    }
+}
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// The message in the current language, or the message itself if it has no translation
+pub fn translate(message: &str) -> &str {
+    match lang() {
+        Lang::En => message,
+        Lang::Ja => ja::translate(message).unwrap_or(message),
+    }
+}
+
+/// Replace each `{}` in `template` with the next argument
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(i) = rest.find("{}") {
+        out.push_str(&rest[..i]);
+        match args.next() {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str("{}"),
+        }
+        rest = &rest[i + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Translate a message literal, filling `{}` placeholders with the arguments
+macro_rules! tr {
+    ($message:literal) => {
+        $crate::i18n::translate($message)
+    };
+    ($message:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::fill($crate::i18n::translate($message), &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
+
+pub(crate) use tr;
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use btleplug::api::{Central, CentralEvent, CentralState, Manager as _};
+use btleplug::platform::{Adapter, Manager};
+use futures::StreamExt;
+
+use super::error::{IQOSError, Result};
+use crate::i18n::tr;
+
+/// A Bluetooth adapter and what the platform reports about it
+pub struct AdapterEntry {
+    pub adapter: Adapter,
+    /// Short name such as `hci0`
+    pub name: String,
+    /// Full platform description
+    pub info: String,
+    pub state: CentralState,
+}
+
+/// List the Bluetooth adapters on this machine
+pub async fn list_adapters(manager: &Manager) -> Result<Vec<AdapterEntry>> {
+    let mut entries = Vec::new();
+    for (index, adapter) in manager.adapters().await?.into_iter().enumerate() {
+        let info = adapter.adapter_info().await.unwrap_or_else(|_| format!("adapter {}", index));
+        let name = info.split_whitespace().next().unwrap_or_default().to_string();
+        let state = adapter.adapter_state().await.unwrap_or(CentralState::Unknown);
+        entries.push(AdapterEntry { adapter, name, info, state });
+    }
+    Ok(entries)
+}
+
+/// Select an adapter by name (`hci1`) or index (`1`), or the first one when
+/// no selector is given
+pub async fn select_adapter(manager: &Manager, selector: Option<&str>) -> Result<Adapter> {
+    let entries = list_adapters(manager).await?;
+    if entries.is_empty() {
+        return Err(IQOSError::AdapterError("No Bluetooth adapter found".to_string()));
+    }
+
+    let Some(selector) = selector else {
+        return Ok(entries.into_iter().next().unwrap().adapter);
+    };
+
+    let index = selector.parse::<usize>().ok();
+    let names = entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>().join(", ");
+    entries.into_iter()
+        .enumerate()
+        .find(|(i, e)| e.name == selector || Some(*i) == index)
+        .map(|(_, e)| e.adapter)
+        .ok_or_else(|| IQOSError::AdapterError(tr!("Adapter '{}' not found. Available adapters: {}", selector, names)))
+}
+
+pub async fn is_powered_on(adapter: &Adapter) -> Result<bool> {
+    Ok(matches!(adapter.adapter_state().await?, CentralState::PoweredOn))
+}
+
+/// Wait until the adapter reports that it is powered on.
+///
+/// Listens for state updates and also polls, since not every platform sends
+/// an event when the adapter comes back.
+pub async fn wait_until_powered_on(adapter: &Adapter) -> Result<()> {
+    let mut events = adapter.events().await?;
+    let mut poll = tokio::time::interval(Duration::from_secs(2));
+
+    loop {
+        if is_powered_on(adapter).await? {
+            return Ok(());
+        }
+        tokio::select! {
+            event = events.next() => match event {
+                Some(CentralEvent::StateUpdate(CentralState::PoweredOn)) => return Ok(()),
+                Some(_) => {}
+                None => return Err(IQOSError::AdapterError("Adapter event stream closed".to_string())),
+            },
+            _ = poll.tick() => {}
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
 use crate::iqos::error::{IQOSError, Result};
 
 pub const BRIGHTNESS_HIGH_SIGNAL: [&[u8]; 3] = [
@@ -14,7 +17,8 @@ pub const BRIGHTNESS_LOW_SIGNAL: [&[u8]; 3] = [
 
 pub const LOAD_BRIGHTNESS_SIGNAL: [u8; 5] = [0x00, 0xc0, 0x02, 0x23, 0xC3];
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BrightnessLevel {
     High,
     Low,
@@ -54,9 +58,9 @@ impl std::str::FromStr for BrightnessLevel {
 impl std::fmt::Display for BrightnessLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let brightness_level = match self {
-            BrightnessLevel::High => "high",
-            BrightnessLevel::Low => "low",
+            BrightnessLevel::High => tr!("high"),
+            BrightnessLevel::Low => tr!("low"),
         };
-        write!(f, "\nBrightness Level: {}\n", brightness_level)
+        write!(f, "\n{}\n", tr!("Brightness Level: {}", brightness_level))
     }
 }
\ No newline at end of file
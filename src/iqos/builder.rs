@@ -1,18 +1,74 @@
 use super::iluma::IlumaSpecific;
-use super::iqos::{IQOSModel, IqosBle};
+use super::iqos::{next_reply, IQOSModel, IqosBle, IqosDevice};
 use super::error::{IQOSError, Result};
-use super::device::{Iqos, IqosIluma};
+use super::frame;
+use crate::i18n::tr;
 use super::{
     BATTERY_CHARACTERISTIC_UUID, CORE_SERVICE_UUID, DEVICE_INFO_SERVICE_UUID, MANUFACTURER_NAME_CHAR_UUID, MODEL_NUMBER_CHAR_UUID, SERIAL_NUMBER_CHAR_UUID, SOFTWARE_REVISION_CHAR_UUID, SCP_CONTROL_CHARACTERISTIC_UUID, PRODUCT_NUM_SIGNAL, HOLDER_PRODUCT_NUM_SIGNAL
 };
 use btleplug::platform::Peripheral;
 use btleplug::api::{Characteristic, Peripheral as _, Service};
 use std::collections::BTreeSet;
-use uuid::Uuid;
-use futures::StreamExt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::time::Duration;
+use tracing::{debug, instrument, trace, warn};
 
-pub struct IQOSBuilder {
-    peripheral: Option<Peripheral>,
+/// Builder state: the peripheral has not been connected yet
+pub struct Disconnected;
+/// Builder state: connected, services not discovered yet
+pub struct Connected;
+/// Builder state: services discovered, device not initialized yet
+pub struct Discovered;
+/// Builder state: device info and characteristics loaded, ready to build
+pub struct Initialized;
+
+/// Options for `IQOSBuilder::connect_and_build` and the individual build steps
+#[derive(Debug, Clone)]
+pub struct BuilderOptions {
+    /// Time allowed for each step (connect, discovery, each query)
+    pub timeout: Duration,
+    /// Skip the holder product number query
+    pub skip_holder_query: bool,
+    /// Use this model instead of guessing it from the advertised name
+    pub model: Option<IQOSModel>,
+}
+
+impl Default for BuilderOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            skip_holder_query: false,
+            model: None,
+        }
+    }
+}
+
+impl BuilderOptions {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_skip_holder_query(mut self, skip: bool) -> Self {
+        self.skip_holder_query = skip;
+        self
+    }
+
+    pub fn with_model(mut self, model: IQOSModel) -> Self {
+        self.model = Some(model);
+        self
+    }
+}
+
+/// Builds an `IqosDevice` from a peripheral.
+///
+/// The state parameter enforces the order connect → discover_services →
+/// initialize → build at compile time. Use `connect_and_build` to run all
+/// steps at once.
+pub struct IQOSBuilder<S = Disconnected> {
+    peripheral: Peripheral,
+    options: BuilderOptions,
     modelnumber: Option<String>,
     serialnumber: Option<String>,
     softwarerevision: Option<String>,
@@ -21,12 +77,18 @@ pub struct IQOSBuilder {
     scp_control_characteristic: Option<Characteristic>,
     product_number: Option<String>,
     iluma: Option<IlumaSpecific>,
+    state: PhantomData<S>,
 }
 
-impl IQOSBuilder {
+impl IQOSBuilder<Disconnected> {
     pub fn new(peripheral: Peripheral) -> Self {
+        Self::with_options(peripheral, BuilderOptions::default())
+    }
+
+    pub fn with_options(peripheral: Peripheral, options: BuilderOptions) -> Self {
         Self {
-            peripheral: Some(peripheral),
+            peripheral,
+            options,
             modelnumber: None,
             serialnumber: None,
             softwarerevision: None,
@@ -35,180 +97,178 @@ impl IQOSBuilder {
             scp_control_characteristic: None,
             product_number: None,
             iluma: None,
+            state: PhantomData,
         }
     }
 
-    pub async fn initialize(&mut self) -> Result<()> {
-        
+    /// Run every step and return the connected device
+    pub async fn connect_and_build(peripheral: Peripheral, options: BuilderOptions) -> Result<IqosDevice> {
+        Self::with_options(peripheral, options)
+            .connect().await?
+            .discover_services().await?
+            .initialize().await?
+            .build().await
+    }
+
+    #[instrument(level = "debug", skip_all, fields(peripheral = %self.peripheral.id()))]
+    pub async fn connect(self) -> Result<IQOSBuilder<Connected>> {
+        self.timed("connect", self.peripheral.connect()).await?
+            .map_err(IQOSError::BleError)?;
+        Ok(self.into_state())
+    }
+}
+
+impl IQOSBuilder<Connected> {
+    #[instrument(level = "debug", skip_all, fields(peripheral = %self.peripheral.id()))]
+    pub async fn discover_services(self) -> Result<IQOSBuilder<Discovered>> {
+        self.timed("discover services", self.peripheral.discover_services()).await?
+            .map_err(IQOSError::BleError)?;
+        Ok(self.into_state())
+    }
+}
+
+impl IQOSBuilder<Discovered> {
+    pub fn services(&self) -> BTreeSet<Service> {
+        self.peripheral.services()
+    }
+
+    #[instrument(level = "debug", skip_all, fields(peripheral = %self.peripheral.id()))]
+    pub async fn initialize(mut self) -> Result<IQOSBuilder<Initialized>> {
         self.load_device_info().await?;
-        self.load_characteristics().await?;
+        self.load_characteristics()?;
+        self.validate()?;
 
-        self.subscribe(self.scp_control_characteristic.as_ref()
-            .ok_or(IQOSError::ConfigurationError("SCP Control characteristic is required".to_string()))?).await?;
+        self.subscribe(self.scp_control_characteristic.as_ref().expect("checked by validate")).await?;
 
         self.load_product_num().await?;
-        self.load_holder_product_num().await?;
-        
-        Ok(())
+        if !self.options.skip_holder_query {
+            self.load_holder_product_num().await?;
+        }
+
+        Ok(self.into_state())
     }
 
     async fn write(&self, byte: Vec<u8>) -> Result<()> {
-        let peripheral = self.peripheral
-            .as_ref()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-        
-        peripheral.write(
-            self.scp_control_characteristic.as_ref().ok_or(IQOSError::ConfigurationError("SCP Control characteristic is required".to_string()))?,
+        trace!(frame = %frame::hex(&byte), command = %frame::describe(&byte), "write");
+        self.peripheral.write(
+            self.scp_control()?,
             &byte,
             btleplug::api::WriteType::WithResponse,
         ).await.map_err(IQOSError::BleError)?;
-        
-        Ok(())
-    }
 
-    async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
-        let peripheral = self.peripheral
-            .as_ref()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-        
-        peripheral.subscribe(characteristic).await
-            .map_err(IQOSError::BleError)?;
-        
         Ok(())
     }
 
-    pub async fn discover_services(&mut self) -> Result<BTreeSet<Service>> {
-        let peripheral = self.peripheral
-            .as_mut()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-        
-        peripheral.discover_services().await
-            .map_err(IQOSError::BleError)?;
-        
-        Ok(peripheral.services().into_iter().collect())
+    fn scp_control(&self) -> Result<&Characteristic> {
+        self.scp_control_characteristic.as_ref()
+            .ok_or(IQOSError::ConfigurationError("SCP Control characteristic is required".to_string()))
     }
 
-    pub async fn connect(&mut self) -> Result<()> {
-        let peripheral = self.peripheral
-            .as_mut()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-        
-        peripheral.connect().await
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<()> {
+        self.peripheral.subscribe(characteristic).await
             .map_err(IQOSError::BleError)?;
-        Ok(())
-    }
 
-    pub async fn is_connected(&self) -> Result<bool> {
-        let peripheral = self.peripheral
-            .as_ref()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-        
-        peripheral.is_connected().await
-            .map_err(IQOSError::BleError)
+        Ok(())
     }
 
     async fn load_product_num(&mut self) -> Result<()> {
-        let peripheral = self.peripheral
-            .as_ref()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-
+        // Subscribe first, so a quick reply is not missed
+        let mut stream = self.peripheral.notifications().await?;
         self.write(PRODUCT_NUM_SIGNAL.to_vec()).await?;
 
-        let mut stream = peripheral.notifications().await?;
-        
-        if let Some(notification) = stream.next().await {
-            
+        let reply = next_reply(&mut stream, self.scp_control()?, &PRODUCT_NUM_SIGNAL);
+        if let Some(value) = self.timed("product number query", reply).await? {
+            trace!(frame = %frame::hex(&value), "reply");
+
             let prefix: [u8; 4] = [0x00, 0xc0, 0x88, 0x03];
-            
-            if notification.value.len() >= 4 && notification.value[0..4] == prefix {
-                let product_num = &notification.value[4..notification.value.len() - 1];
-                
+
+            if value.len() >= 4 && value[0..4] == prefix {
+                let product_num = &value[4..value.len() - 1];
+
                 let ascii_string = product_num.iter()
                     .map(|&b| if b.is_ascii() && !b.is_ascii_control() { b as char } else { '.' })
                     .collect::<String>();
+                debug!(product_number = %ascii_string);
                 self.product_number = Some(ascii_string);
             }
         }
-        
+
         Ok(())
     }
 
     async fn load_holder_product_num(&mut self) -> Result<()> {
-        let peripheral = self.peripheral
-            .as_ref()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-
+        // Subscribe first, so a quick reply is not missed
+        let mut stream = self.peripheral.notifications().await?;
         self.write(HOLDER_PRODUCT_NUM_SIGNAL.to_vec()).await?;
 
-        let mut stream = peripheral.notifications().await?;
-
-        if let Some(notification) = stream.next().await {
+        let reply = next_reply(&mut stream, self.scp_control()?, &HOLDER_PRODUCT_NUM_SIGNAL);
+        if let Some(value) = self.timed("holder product number query", reply).await? {
+            trace!(frame = %frame::hex(&value), "reply");
             let prefix: [u8; 4] = [0x00, 0x08, 0x88, 0x03];
-            
-            if notification.value.len() >= 4 && notification.value[0..4] == prefix {
-                let product_num = &notification.value[4..];
-                
+
+            if value.len() >= 4 && value[0..4] == prefix {
+                let product_num = &value[4..];
+
                 let ascii_string = product_num.iter()
                     .map(|&b| if b.is_ascii() && !b.is_ascii_control() { b as char } else { '.' })
                     .collect::<String>();
-                println!("Holder Product Number: {}", ascii_string);
+                debug!(holder_product_number = %ascii_string);
                 self.iluma = Some(IlumaSpecific::new(ascii_string, "".to_string()));
             }
         }
-        
+
         Ok(())
     }
 
     async fn load_device_info(&mut self) -> Result<()> {
-        let peripheral = self.peripheral
-            .as_mut()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-        
-        if let Some(service) = peripheral.services().iter().find(|s| s.uuid == DEVICE_INFO_SERVICE_UUID) {
-            for characteristic in &service.characteristics {
-                match characteristic.uuid.to_string().split('-').next().unwrap() {
-                    uuid if uuid == MODEL_NUMBER_CHAR_UUID => {
-                        if let Ok(data) = peripheral.read(characteristic).await.map_err(IQOSError::BleError) {
-                            if let Ok(value) = String::from_utf8(data) {
-                                self.modelnumber = Some(value);
-                            }
-                        }
-                    },
-                    uuid if uuid == SERIAL_NUMBER_CHAR_UUID => {
-                        if let Ok(data) = peripheral.read(characteristic).await.map_err(IQOSError::BleError) {
-                            if let Ok(value) = String::from_utf8(data) {
-                                self.serialnumber = Some(value);
-                            }
-                        }
-                    },
-                    uuid if uuid == SOFTWARE_REVISION_CHAR_UUID => {
-                        if let Ok(data) = peripheral.read(characteristic).await.map_err(IQOSError::BleError) {
-                            if let Ok(value) = String::from_utf8(data) {
-                                self.softwarerevision = Some(value);
-                            }
-                        }
-                    },
-                    uuid if uuid == MANUFACTURER_NAME_CHAR_UUID => {
-                        if let Ok(data) = peripheral.read(characteristic).await.map_err(IQOSError::BleError) {
-                            if let Ok(value) = String::from_utf8(data) {
-                                self.manufacturername = Some(value);
-                            }
-                        }
-                    },
-                    _ => {}
-                }
+        let Some(service) = self.peripheral.services().into_iter().find(|s| s.uuid == DEVICE_INFO_SERVICE_UUID) else {
+            warn!("device information service not found");
+            return Ok(());
+        };
+
+        for characteristic in &service.characteristics {
+            let name = match characteristic.uuid.to_string().split('-').next().unwrap() {
+                uuid if uuid == MODEL_NUMBER_CHAR_UUID => "model number",
+                uuid if uuid == SERIAL_NUMBER_CHAR_UUID => "serial number",
+                uuid if uuid == SOFTWARE_REVISION_CHAR_UUID => "software revision",
+                uuid if uuid == MANUFACTURER_NAME_CHAR_UUID => "manufacturer name",
+                _ => continue,
+            };
+            let Some(value) = self.read_string(characteristic, name).await? else {
+                continue;
+            };
+            match name {
+                "model number" => self.modelnumber = Some(value),
+                "serial number" => self.serialnumber = Some(value),
+                "software revision" => self.softwarerevision = Some(value),
+                _ => self.manufacturername = Some(value),
             }
         }
-        
+
         Ok(())
     }
 
-    async fn load_characteristics(&mut self) -> Result<()> {
-        let peripheral = self.peripheral
-            .as_mut()
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
-        
-        if let Some(service) = peripheral.services().iter().find(|s| s.uuid == CORE_SERVICE_UUID) {
+    /// Read a text characteristic. Failed reads are logged and skipped, as
+    /// only the serial number is required; a read that hangs is a timeout.
+    async fn read_string(&self, characteristic: &Characteristic, name: &str) -> Result<Option<String>> {
+        let data = match self.timed(&format!("{} read", name), self.peripheral.read(characteristic)).await? {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(characteristic = %characteristic.uuid, error = %e, "could not read {}", name);
+                return Ok(None);
+            },
+        };
+        match String::from_utf8(data) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                warn!(characteristic = %characteristic.uuid, error = %e, "{} is not UTF-8", name);
+                Ok(None)
+            },
+        }
+    }
+
+    fn load_characteristics(&mut self) -> Result<()> {
+        if let Some(service) = self.peripheral.services().iter().find(|s| s.uuid == CORE_SERVICE_UUID) {
             for characteristic in &service.characteristics {
                 let uuid = characteristic.uuid;
                 if uuid == BATTERY_CHARACTERISTIC_UUID {
@@ -218,32 +278,90 @@ impl IQOSBuilder {
                 }
             }
         }
-        
+
         Ok(())
     }
 
-    pub async fn build(self) -> Result<IqosBle> {
-        let peripheral = self.peripheral
-            .ok_or(IQOSError::ConfigurationError("Peripheral is required".to_string()))?;
+    /// Fail with the missing piece, before any query is sent
+    fn validate(&self) -> Result<()> {
+        if self.serialnumber.is_none() {
+            return Err(IQOSError::ConfigurationError(tr!(
+                "The device did not report a serial number (characteristic {} in service {})",
+                SERIAL_NUMBER_CHAR_UUID, DEVICE_INFO_SERVICE_UUID,
+            )));
+        }
+        if self.battery_characteristic.is_none() {
+            return Err(IQOSError::ConfigurationError(tr!(
+                "The battery characteristic {} was not found in service {}",
+                BATTERY_CHARACTERISTIC_UUID, CORE_SERVICE_UUID,
+            )));
+        }
+        if self.scp_control_characteristic.is_none() {
+            return Err(IQOSError::ConfigurationError(tr!(
+                "The SCP control characteristic {} was not found in service {}",
+                SCP_CONTROL_CHARACTERISTIC_UUID, CORE_SERVICE_UUID,
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl IQOSBuilder<Initialized> {
+    pub async fn build(self) -> Result<IqosDevice> {
+        let model = match self.options.model {
+            Some(model) => model,
+            None => IQOSModel::from_peripheral(&self.peripheral).await,
+        };
 
         let iqos = IqosBle::new(
-            peripheral,
+            self.peripheral,
+            model,
             self.modelnumber.unwrap_or_else(|| "Unknown".to_string()),
-            self.serialnumber.ok_or(IQOSError::ConfigurationError("Serial number is required".to_string()))?,
+            self.serialnumber.expect("checked by initialize"),
             self.softwarerevision.unwrap_or_else(|| "Unknown".to_string()),
             self.manufacturername.unwrap_or_else(|| "Unknown".to_string()),
-            self.battery_characteristic.ok_or(IQOSError::ConfigurationError("Battery characteristic is required".to_string()))?,
-            self.scp_control_characteristic.ok_or(IQOSError::ConfigurationError("SCP Control characteristic is required".to_string()))?,
+            self.battery_characteristic.expect("checked by initialize"),
+            self.scp_control_characteristic.expect("checked by initialize"),
             self.product_number.unwrap_or_else(|| "Unknown".to_string()),
             self.iluma,
-        ).await;
+        );
 
-        Ok(iqos)
+        Ok(IqosDevice::from_ble(iqos))
     }
 }
 
-// impl Default for IQOSBuilder {
-//     fn default() -> Self {
-//         Self::new(Peripheral::default())
-//     }
-// }
\ No newline at end of file
+impl<S> IQOSBuilder<S> {
+    pub fn peripheral(&self) -> &Peripheral {
+        &self.peripheral
+    }
+
+    pub fn options(&self) -> &BuilderOptions {
+        &self.options
+    }
+
+    pub async fn is_connected(&self) -> Result<bool> {
+        self.peripheral.is_connected().await
+            .map_err(IQOSError::BleError)
+    }
+
+    async fn timed<F: Future>(&self, step: &str, future: F) -> Result<F::Output> {
+        tokio::time::timeout(self.options.timeout, future).await
+            .map_err(|_| IQOSError::TimeoutError(tr!("{} did not finish within {}", step, format!("{:?}", self.options.timeout))))
+    }
+
+    fn into_state<T>(self) -> IQOSBuilder<T> {
+        IQOSBuilder {
+            peripheral: self.peripheral,
+            options: self.options,
+            modelnumber: self.modelnumber,
+            serialnumber: self.serialnumber,
+            softwarerevision: self.softwarerevision,
+            manufacturername: self.manufacturername,
+            battery_characteristic: self.battery_characteristic,
+            scp_control_characteristic: self.scp_control_characteristic,
+            product_number: self.product_number,
+            iluma: self.iluma,
+            state: PhantomData,
+        }
+    }
+}
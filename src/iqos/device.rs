@@ -1,15 +1,125 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::i18n::tr;
+
 use super::error::Result;
+use super::events::EventSource;
+use super::iqos::IQOSModel;
 use super::brightness::BrightnessLevel;
-use super::vibration::{IlumaVibrationBehavior, VibrationSettings};
+use super::vibration::VibrationSettings;
 use super::flexbattery::FlexBattery;
 use super::flexpuff::Flexpuff;
 
-pub trait Iqos {
+/// A device shared between the console, commands and background tasks.
+///
+/// Any `Iqos` implementation can be held here: typed BLE handles, simulators
+/// or wrappers.
+pub type SharedIqos = Arc<Mutex<dyn Iqos>>;
+
+/// Identification and status of a connected device, as shown by `info`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub model: IQOSModel,
+    pub model_number: String,
+    pub serial_number: String,
+    pub software_revision: String,
+    pub manufacturer_name: String,
+    pub product_number: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub holder_product_number: Option<String>,
+    pub battery: u8,
+}
+
+impl std::fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(holder_product_number) = &self.holder_product_number {
+            return write!(
+                f,
+                "{}",
+                tr!(
+                    "Model: {}\nModel Number: {}\nSerial Number: {}\nManufacturer Name: {}\n\nStick:\n\tProduct Number: {}\n\tSoftware Revision: {}\nHolder:\n\tHolder Product Number: {}",
+                    self.model,
+                    self.model_number,
+                    self.serial_number,
+                    self.manufacturer_name,
+                    self.product_number,
+                    self.software_revision,
+                    holder_product_number,
+                ),
+            )
+        }
+        write!(
+            f,
+            "{}",
+            tr!(
+                "Model: {}\nModel Number: {}\nSerial Number: {}\nSoftware Revision: {}\nManufacturer Name: {}\nProduct Number: {}",
+                self.model,
+                self.model_number,
+                self.serial_number,
+                self.software_revision,
+                self.manufacturer_name,
+                self.product_number,
+            ),
+        )
+    }
+}
+
+/// Operations common to every IQOS model.
+///
+/// The traits are object safe, so `Box<dyn Iqos>` and `Arc<Mutex<dyn Iqos>>`
+/// work. Model specific features are reached through `as_iluma` and
+/// `as_iluma_i`, which return `None` when the device does not support them.
+#[async_trait]
+pub trait Iqos: std::fmt::Display + Send + Sync {
+    fn model(&self) -> &IQOSModel;
+
+    /// Serial number read from the device information service. Stable even
+    /// when the BLE address rotates.
+    fn serial_number(&self) -> &str;
+
+    fn device_info(&self) -> DeviceInfo;
+
+    /// Where to follow battery changes and disconnects, for devices that report them
+    fn event_source(&self) -> Option<EventSource> {
+        None
+    }
+
+    /// In a dry run, frames are recorded instead of written and reads are
+    /// answered with cached or simulated replies
+    fn set_dry_run(&self, _enabled: bool) {}
+
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+
+    /// Descriptions of the frames a dry run kept from the device since the
+    /// last call, for the caller to show
+    fn take_dry_run_frames(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn as_iluma(&self) -> Option<&dyn IqosIluma> {
+        None
+    }
+
+    fn as_iluma_i(&self) -> Option<&dyn IqosIlumaI> {
+        None
+    }
+
     async fn disconnect(&mut self) -> Result<()>;
     
     async fn reload_battery(&mut self) -> Result<()>;
     
     fn battery_status(&self) -> u8;
+
+    /// Level of the charger case, on models that report it
+    fn charger_battery_status(&self) -> Option<u8> {
+        None
+    }
     
     async fn vibrate(&self) -> Result<()>;
     
@@ -28,6 +138,7 @@ pub trait Iqos {
     async fn update_vibration_settings(&self, settings: VibrationSettings) -> Result<()>;
 }
 
+#[async_trait]
 pub trait IqosIluma: Send + Sync {
     async fn load_iluma_vibration_settings(&self) -> Result<VibrationSettings>;
 
@@ -41,7 +152,8 @@ pub trait IqosIluma: Send + Sync {
     async fn update_flexpuff(&self, setting: Flexpuff) -> Result<()>;
 }
 
-pub trait IqosIlumaI {
+#[async_trait]
+pub trait IqosIlumaI: Send + Sync {
     async fn update_flexbattery(&self, new: FlexBattery) -> Result<()>;
     async fn load_flexbattery(&self) -> Result<FlexBattery>;
-}
\ No newline at end of file
+}
@@ -2,6 +2,8 @@ use std::error::Error;
 use std::fmt;
 
 use btleplug::Error as BleError;
+
+use crate::i18n::{tr, translate};
 use super::iluma::NotIlumaError;
 
 #[derive(Debug)]
@@ -11,18 +13,20 @@ pub enum IQOSError {
     ConfigurationError(String),
     AutoStartError(String),
     AdapterError(String),
-    IncompatibleModelError, // 互換性エラーを追加
+    TimeoutError(String),
+    IncompatibleModelError,
 }
 
 impl fmt::Display for IQOSError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            IQOSError::BleError(err) => write!(f, "Bluetooth error: {}", err),
+            IQOSError::BleError(err) => write!(f, "{}", tr!("Bluetooth error: {}", err)),
             IQOSError::NotIluma(err) => write!(f, "{}", err),
-            IQOSError::ConfigurationError(msg) => write!(f, "Configuration error: {}", msg),
-            IQOSError::AutoStartError(msg) => write!(f, "AutoStart error: {}", msg),
-            IQOSError::AdapterError(msg) => write!(f, "Adapter error: {}", msg),
-            IQOSError::IncompatibleModelError => write!(f, "Incompatible model error"),
+            IQOSError::ConfigurationError(msg) => write!(f, "{}", tr!("Configuration error: {}", translate(msg))),
+            IQOSError::AutoStartError(msg) => write!(f, "{}", tr!("AutoStart error: {}", translate(msg))),
+            IQOSError::AdapterError(msg) => write!(f, "{}", tr!("Adapter error: {}", translate(msg))),
+            IQOSError::TimeoutError(msg) => write!(f, "{}", tr!("Timed out: {}", translate(msg))),
+            IQOSError::IncompatibleModelError => write!(f, "{}", tr!("Incompatible model error")),
         }
     }
 }
@@ -35,6 +39,7 @@ impl Error for IQOSError {
             IQOSError::ConfigurationError(_) => None,
             IQOSError::AutoStartError(_) => None,
             IQOSError::AdapterError(_) => None,
+            IQOSError::TimeoutError(_) => None,
             IQOSError::IncompatibleModelError => None,
         }
     }
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use btleplug::api::{CharPropFlags, Characteristic, Peripheral as _};
+use btleplug::platform::Peripheral;
+use futures::StreamExt;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::trace;
+
+use super::error::Result;
+use super::frame;
+
+/// How often the connection is checked, as BlueZ does not notify a peripheral
+/// handle about disconnects
+const CONNECTION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the battery is read when the characteristic cannot notify
+const BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Something the device reported without being asked
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DeviceEvent {
+    /// The holder or charger battery level changed
+    Battery {
+        percent: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        charger: Option<u8>,
+    },
+    /// The connection was lost. No events follow.
+    Disconnected,
+    /// A frame on the SCP control characteristic, including replies to commands
+    Notification { data: Vec<u8> },
+}
+
+/// Battery levels in a value of the battery characteristic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryLevels {
+    /// Byte 2
+    pub holder: u8,
+    /// Byte 3, on models with a charger case. Values above 100 mean there is
+    /// no reading.
+    pub charger: Option<u8>,
+}
+
+impl BatteryLevels {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let holder = *data.get(2)?;
+        let charger = data.get(3).copied().filter(|level| *level <= 100);
+        Some(Self { holder, charger })
+    }
+}
+
+/// What is needed to follow a device's events without locking the device
+#[derive(Clone)]
+pub struct EventSource {
+    peripheral: Peripheral,
+    battery_characteristic: Characteristic,
+}
+
+impl EventSource {
+    pub(crate) fn new(peripheral: Peripheral, battery_characteristic: Characteristic) -> Self {
+        Self { peripheral, battery_characteristic }
+    }
+
+    /// Report events from a background task. The task stops after the device
+    /// disconnects or when every receiver is dropped; use `resubscribe` for
+    /// more receivers.
+    pub fn spawn(self) -> broadcast::Receiver<DeviceEvent> {
+        let (sender, receiver) = broadcast::channel(64);
+        tokio::spawn(async move {
+            let _ = self.run(sender).await;
+        });
+        receiver
+    }
+
+    async fn run(self, sender: broadcast::Sender<DeviceEvent>) -> Result<()> {
+        let battery_notifies = self.battery_characteristic.properties.contains(CharPropFlags::NOTIFY)
+            && self.peripheral.subscribe(&self.battery_characteristic).await.is_ok();
+
+        let mut notifications = self.peripheral.notifications().await?;
+        let mut connection_poll = tokio::time::interval(CONNECTION_POLL_INTERVAL);
+        let mut battery_poll = tokio::time::interval(BATTERY_POLL_INTERVAL);
+        let mut battery = None;
+
+        loop {
+            let event = tokio::select! {
+                notification = notifications.next() => match notification {
+                    Some(n) if n.uuid == self.battery_characteristic.uuid => battery_event(&n.value, &mut battery),
+                    Some(n) => {
+                        trace!(frame = %frame::hex(&n.value), "notification");
+                        Some(DeviceEvent::Notification { data: n.value })
+                    },
+                    None => Some(DeviceEvent::Disconnected),
+                },
+                _ = connection_poll.tick() => match self.peripheral.is_connected().await {
+                    Ok(false) => Some(DeviceEvent::Disconnected),
+                    _ => None,
+                },
+                _ = battery_poll.tick(), if !battery_notifies => match self.peripheral.read(&self.battery_characteristic).await {
+                    Ok(data) => battery_event(&data, &mut battery),
+                    Err(_) => None,
+                },
+            };
+
+            let Some(event) = event else { continue };
+            let disconnected = event == DeviceEvent::Disconnected;
+            if sender.send(event).is_err() || disconnected {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A battery event if the levels in `data` differ from the last ones seen
+fn battery_event(data: &[u8], last: &mut Option<BatteryLevels>) -> Option<DeviceEvent> {
+    let levels = BatteryLevels::from_bytes(data)?;
+    if *last == Some(levels) {
+        return None;
+    }
+    *last = Some(levels);
+    Some(DeviceEvent::Battery { percent: levels.holder, charger: levels.charger })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battery_events_only_on_change() {
+        let mut last = None;
+        assert_eq!(battery_event(&[0, 0, 80, 50], &mut last), Some(DeviceEvent::Battery { percent: 80, charger: Some(50) }));
+        assert_eq!(battery_event(&[0, 0, 80, 50], &mut last), None);
+        assert_eq!(battery_event(&[0, 0, 80, 0xFF], &mut last), Some(DeviceEvent::Battery { percent: 80, charger: None }));
+        assert_eq!(battery_event(&[0, 0], &mut last), None);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::i18n::tr;
 use crate::iqos::error::{IQOSError, Result};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 pub const LOAD_FLEXBATTERY_SIGNAL: [u8; 5] = [0x00, 0xC9, 0x00, 0x25, 0xFB];
@@ -27,7 +29,8 @@ pub const PAUSEMODE_ENABLE_SIGNALS: [&[u8]; 2] = [
 
 pub type Pausemode = bool;
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FlexbatteryMode {
     #[default]
     Performance,
@@ -43,9 +46,10 @@ impl FlexbatteryMode {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct FlexBattery {
     mode: FlexbatteryMode,
+    #[serde(rename = "pause_mode", skip_serializing_if = "Option::is_none")]
     is_pause_mode: Option<Pausemode>,
 }
 
@@ -156,7 +160,7 @@ impl FlexBattery {
                     i += 2;
                 },
                 _ => {
-                    return Err(IQOSError::ConfigurationError(format!("Unknown argument: {}", arg)));
+                    return Err(IQOSError::ConfigurationError(tr!("Unknown argument: {}", arg)));
                 }
             }
         }
@@ -171,17 +175,17 @@ impl FlexBattery {
 impl fmt::Display for FlexbatteryMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            FlexbatteryMode::Eco => write!(f, "Eco"),
-            FlexbatteryMode::Performance => write!(f, "Performance"),
+            FlexbatteryMode::Eco => write!(f, "{}", tr!("Eco")),
+            FlexbatteryMode::Performance => write!(f, "{}", tr!("Performance")),
         }
     }
 }
 
 impl fmt::Display for FlexBattery {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Flexbattery: {}", self.mode)?;
+        write!(f, "{}", tr!("Flexbattery: {}", self.mode))?;
         if let Some(pause_mode) = self.is_pause_mode {
-            write!(f, "\nPause mode: {}", if pause_mode { "On" } else { "Off" })?;
+            write!(f, "\n{}", tr!("Pause mode: {}", if pause_mode { tr!("On") } else { tr!("Off") }))?;
         }
         Ok(())
     }
@@ -3,5 +3,6 @@ mod flexbattery;
 pub use flexbattery::{
     FlexBattery, FlexbatteryMode, Pausemode, 
     LOAD_FLEXBATTERY_SIGNAL, LOAD_PAUSEMODE_SIGNAL,
-    FLEXBATTERY_ECO_SIGNALS, FLEXBATTERY_PERFORMANCE_SIGNALS
+    FLEXBATTERY_ECO_SIGNALS, FLEXBATTERY_PERFORMANCE_SIGNALS,
+    PAUSEMODE_ENABLE_SIGNALS, PAUSEMODE_DISABLE_SIGNALS
 };
\ No newline at end of file
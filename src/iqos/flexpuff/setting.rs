@@ -1,11 +1,13 @@
+use crate::i18n::tr;
 use crate::iqos::error::{IQOSError, Result};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 pub const LOAD_FLEXPUFF_SIGNAL: [u8; 9] = [0x00, 0xD2, 0x05, 0x22, 0x03, 0x00, 0x00, 0x00, 0x17];
 const FLEXPUFF_ENABLE_SIGNAL: [u8; 9] = [0x00, 0xD2, 0x45, 0x22, 0x03, 0x01, 0x00, 0x00, 0x0A];
 const FLEXPUFF_DISABLE_SIGNAL: [u8; 9] = [0x00, 0xD2, 0x45, 0x22, 0x03, 0x00, 0x00, 0x00, 0x0A];
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Flexpuff {
     enabled: bool,
 }
@@ -15,6 +17,10 @@ impl Flexpuff {
         Self { enabled }
     }
 
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
         if bytes.len() < 9 {
             return Err(IQOSError::ConfigurationError("Invalid Flexpuff data".to_string()));
@@ -56,6 +62,6 @@ impl Flexpuff {
 
 impl fmt::Display for Flexpuff {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Flexpuff is {}", if self.enabled { "enabled" } else { "disabled" })
+        write!(f, "{}", if self.enabled { tr!("Flexpuff is enabled") } else { tr!("Flexpuff is disabled") })
     }
 }
\ No newline at end of file
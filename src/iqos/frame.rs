@@ -0,0 +1,204 @@
+//! Decoding of SCP control frames, for dry runs and logs.
+
+use super::brightness::{BRIGHTNESS_HIGH_SIGNAL, BRIGHTNESS_LOW_SIGNAL, LOAD_BRIGHTNESS_SIGNAL};
+use super::flexbattery::{
+    FLEXBATTERY_ECO_SIGNALS, FLEXBATTERY_PERFORMANCE_SIGNALS, LOAD_FLEXBATTERY_SIGNAL, LOAD_PAUSEMODE_SIGNAL,
+    PAUSEMODE_DISABLE_SIGNALS, PAUSEMODE_ENABLE_SIGNALS,
+};
+use super::flexpuff::LOAD_FLEXPUFF_SIGNAL;
+use super::iluma::{
+    AUTOSTART_DISABLE_SIGNAL, AUTOSTART_ENABLE_SIGNAL, FLEXPUFF_DISABLE_SIGNAL, FLEXPUFF_ENABLE_SIGNAL,
+    LOAD_VIBRATE_CHARGE_START_SIGNAL, SMARTGESTURE_DISABLE_SIGNAL, SMARTGESTURE_ENABLE_SIGNAL,
+};
+use super::iqos::{CONFIRMATION_SIGNAL, LOCK_SIGNALS, START_VIBRATE_SIGNAL, STOP_VIBRATE_SIGNAL, UNLOCK_SIGNALS};
+use super::vibration::{
+    LOAD_VIBRATION_SETTINGS_SIGNAL, WHEN_CHARGING_START_OFF_SIGNALS, WHEN_CHARGING_START_ON_SIGNALS,
+    WHEN_HEATING_START_SIGNAL, WHEN_MANUALLY_TERMINATED_SIGNAL, WHEN_PUFF_END_SIGNAL, WHEN_STARTING_TO_USE_SIGNAL,
+};
+use super::{HOLDER_PRODUCT_NUM_SIGNAL, PRODUCT_NUM_SIGNAL};
+use crate::i18n::{fill, translate};
+
+/// Set in the third byte of every reply to a request
+const REPLY_FLAG: u8 = 0x80;
+
+/// The address (second byte) requests are sent to, and the one their
+/// replies come from
+const REPLY_ADDRESSES: [(u8, u8); 3] = [(0xC0, 0xC0), (0xC9, 0x08), (0xD2, 0x90)];
+
+/// Header of the frame that writes the vibration register
+const VIBRATION_WRITE_HEADER: [u8; 6] = [0x00, 0xC9, 0x44, 0x23, 0x10, 0x00];
+
+/// Bytes as space separated hex, e.g. `00 C9 00 23 E9`
+pub fn hex(frame: &[u8]) -> String {
+    frame.iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// What sending `frame` does, as far as it is known
+pub fn describe(frame: &[u8]) -> String {
+    describe_in(frame, |message| message)
+}
+
+/// `describe` in the current language, for messages shown to the user
+pub fn describe_translated(frame: &[u8]) -> String {
+    describe_in(frame, translate)
+}
+
+fn describe_in(frame: &[u8], tr: fn(&str) -> &str) -> String {
+    if let Some(description) = describe_vibration(frame, tr) {
+        return description;
+    }
+
+    let known: [(&[u8], &str); 28] = [
+        (&CONFIRMATION_SIGNAL, "confirm the previous command"),
+        (&START_VIBRATE_SIGNAL, "start vibrating"),
+        (&STOP_VIBRATE_SIGNAL, "stop vibrating"),
+        (LOCK_SIGNALS[0], "lock the device"),
+        (UNLOCK_SIGNALS[0], "unlock the device"),
+        (LOCK_SIGNALS[1], "apply the lock state"),
+        (BRIGHTNESS_HIGH_SIGNAL[0], "set brightness to high"),
+        (BRIGHTNESS_HIGH_SIGNAL[2], "set holder brightness to high"),
+        (BRIGHTNESS_LOW_SIGNAL[0], "set brightness to low"),
+        (BRIGHTNESS_LOW_SIGNAL[2], "set holder brightness to low"),
+        (&LOAD_BRIGHTNESS_SIGNAL, "read brightness"),
+        (&LOAD_VIBRATION_SETTINGS_SIGNAL, "read vibration settings"),
+        (&LOAD_VIBRATE_CHARGE_START_SIGNAL, "read vibration when charging starts"),
+        (WHEN_CHARGING_START_ON_SIGNALS[6], "read vibration settings of the holder"),
+        (WHEN_CHARGING_START_ON_SIGNALS[4], "apply the charging vibration setting"),
+        (&SMARTGESTURE_ENABLE_SIGNAL, "enable Smart Gesture"),
+        (&SMARTGESTURE_DISABLE_SIGNAL, "disable Smart Gesture"),
+        (&AUTOSTART_ENABLE_SIGNAL, "enable autostart"),
+        (&AUTOSTART_DISABLE_SIGNAL, "disable autostart"),
+        (&LOAD_FLEXPUFF_SIGNAL, "read FlexPuff"),
+        (&FLEXPUFF_ENABLE_SIGNAL, "enable FlexPuff"),
+        (&FLEXPUFF_DISABLE_SIGNAL, "disable FlexPuff"),
+        (&LOAD_FLEXBATTERY_SIGNAL, "read FlexBattery mode"),
+        (&LOAD_PAUSEMODE_SIGNAL, "read pause mode"),
+        (FLEXBATTERY_ECO_SIGNALS[0], "set FlexBattery to eco"),
+        (FLEXBATTERY_PERFORMANCE_SIGNALS[0], "set FlexBattery to performance"),
+        (PAUSEMODE_ENABLE_SIGNALS[0], "enable pause mode"),
+        (PAUSEMODE_DISABLE_SIGNALS[0], "disable pause mode"),
+    ];
+    let product: [(&[u8], &str); 2] = [
+        (&PRODUCT_NUM_SIGNAL, "read the product number"),
+        (&HOLDER_PRODUCT_NUM_SIGNAL, "read the holder product number"),
+    ];
+
+    known.iter()
+        .chain(product.iter())
+        .find(|(signal, _)| *signal == frame)
+        .map(|(_, description)| tr(description).to_string())
+        .unwrap_or_else(|| tr("unknown frame").to_string())
+}
+
+/// The vibration register write and the charging vibration frames
+fn describe_vibration(frame: &[u8], tr: fn(&str) -> &str) -> Option<String> {
+    if frame == WHEN_CHARGING_START_ON_SIGNALS[0] {
+        return Some(fill(tr("vibrate when charging starts: {}"), &[&tr("on")]));
+    }
+    if frame == WHEN_CHARGING_START_OFF_SIGNALS[0] {
+        return Some(fill(tr("vibrate when charging starts: {}"), &[&tr("off")]));
+    }
+    if frame.len() != 9 || frame[..6] != VIBRATION_WRITE_HEADER {
+        return None;
+    }
+
+    let reg = u16::from_be_bytes([frame[6], frame[7]]);
+    let on_off = |signal: u16| tr(if reg & signal != 0 { "on" } else { "off" });
+    Some(fill(
+        tr("set vibration: heating {}, starting {}, puff end {}, terminated {}"),
+        &[
+            &on_off(WHEN_HEATING_START_SIGNAL),
+            &on_off(WHEN_STARTING_TO_USE_SIGNAL),
+            &on_off(WHEN_PUFF_END_SIGNAL),
+            &on_off(WHEN_MANUALLY_TERMINATED_SIGNAL),
+        ],
+    ))
+}
+
+/// Whether `reply` answers `request`: replies carry the reply flag, come
+/// from the address the request went to and repeat its register bytes
+pub fn is_reply_to(request: &[u8], reply: &[u8]) -> bool {
+    if request.len() < 4 || reply.len() < 4 || reply[2] & REPLY_FLAG == 0 || reply[3] != request[3] {
+        return false;
+    }
+    let from_address = REPLY_ADDRESSES.iter()
+        .find(|(to, _)| *to == request[1])
+        .is_none_or(|(_, from)| *from == reply[1]);
+    // Long requests name a sub-register in the fifth byte
+    let same_subregister = request.len() < 9 || reply.get(4) == Some(&request[4]);
+    from_address && same_subregister
+}
+
+/// A plausible reply to a read request, used in dry runs before the real
+/// value has been read once
+pub fn simulated_reply(request: &[u8]) -> Option<Vec<u8>> {
+    let reply: &[u8] = if request == LOAD_BRIGHTNESS_SIGNAL {
+        // High
+        &[0x00, 0xC0, 0x86, 0x23, 0x64, 0x00, 0x00, 0x00, 0x00]
+    } else if request == LOAD_VIBRATION_SETTINGS_SIGNAL {
+        // Every option on
+        &[0x00, 0x08, 0x84, 0x23, 0x10, 0x00, 0x11, 0x11, 0x00]
+    } else if request == LOAD_VIBRATE_CHARGE_START_SIGNAL {
+        &[0x00, 0x08, 0x8B, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x56]
+    } else if request == LOAD_FLEXPUFF_SIGNAL {
+        // Disabled
+        &[0x00, 0x90, 0x85, 0x22, 0x03, 0x00, 0x00, 0x00, 0x00]
+    } else if request == LOAD_FLEXBATTERY_SIGNAL {
+        // Performance
+        &[0x00, 0x08, 0x84, 0x25, 0x00, 0x00, 0x00, 0x00, 0x00]
+    } else if request == LOAD_PAUSEMODE_SIGNAL {
+        // Off
+        &[0x00, 0x08, 0x87, 0x24, 0x02, 0x00, 0x00, 0x00, 0x00]
+    } else {
+        return None;
+    };
+    Some(reply.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const READ_REQUESTS: [&[u8]; 6] = [
+        &LOAD_BRIGHTNESS_SIGNAL,
+        &LOAD_VIBRATION_SETTINGS_SIGNAL,
+        &LOAD_VIBRATE_CHARGE_START_SIGNAL,
+        &LOAD_FLEXPUFF_SIGNAL,
+        &LOAD_FLEXBATTERY_SIGNAL,
+        &LOAD_PAUSEMODE_SIGNAL,
+    ];
+
+    #[test]
+    fn simulated_replies_answer_their_request() {
+        for request in READ_REQUESTS {
+            let reply = simulated_reply(request).expect("simulated reply");
+            assert!(is_reply_to(request, &reply), "{}", hex(request));
+        }
+    }
+
+    #[test]
+    fn replies_to_other_registers_do_not_match() {
+        let brightness = simulated_reply(&LOAD_BRIGHTNESS_SIGNAL).unwrap();
+        assert!(!is_reply_to(&LOAD_VIBRATION_SETTINGS_SIGNAL, &brightness));
+        assert!(!is_reply_to(&LOAD_FLEXPUFF_SIGNAL, &brightness));
+        let pause_mode = simulated_reply(&LOAD_PAUSEMODE_SIGNAL).unwrap();
+        assert!(!is_reply_to(&LOAD_VIBRATE_CHARGE_START_SIGNAL, &pause_mode));
+    }
+
+    #[test]
+    fn requests_are_not_replies() {
+        for request in READ_REQUESTS {
+            assert!(!is_reply_to(request, request), "{}", hex(request));
+        }
+    }
+
+    #[test]
+    fn product_number_replies_match() {
+        assert!(is_reply_to(&PRODUCT_NUM_SIGNAL, &[0x00, 0xC0, 0x88, 0x03, 0x41, 0x42, 0x00]));
+        assert!(is_reply_to(&HOLDER_PRODUCT_NUM_SIGNAL, &[0x00, 0x08, 0x88, 0x03, 0x41, 0x42]));
+        assert!(!is_reply_to(&PRODUCT_NUM_SIGNAL, &[0x00, 0xC0]));
+    }
+}
@@ -1,13 +1,14 @@
-use futures::{stream, StreamExt};
+use async_trait::async_trait;
+use tracing::{debug, instrument};
 
+use crate::i18n::tr;
 use crate::iqos::error::{IQOSError, Result};
-use crate::iqos::vibration::{self, VibrationSettings, LOAD_VIBRATION_SETTINGS_SIGNAL};
+use crate::iqos::vibration::{VibrationSettings, LOAD_VIBRATION_SETTINGS_SIGNAL};
 
 use super::device::IqosIluma;
-use super::iqos::IqosBle;
+use super::iqos::{forward_iqos, IqosBle};
 use super::vibration::{IlumaVibration, IlumaVibrationBehavior};
 use super::flexpuff::{Flexpuff, LOAD_FLEXPUFF_SIGNAL};
-use btleplug::api::{Peripheral as _, WriteType};
 
 pub struct IlumaSpecific {
     holder_product_number: String,
@@ -35,20 +36,53 @@ pub const AUTOSTART_DISABLE_SIGNAL: [u8; 9] = [0x00, 0xc9, 0x47, 0x24, 0x01, 0x0
 pub const SMARTGESTURE_ENABLE_SIGNAL: [u8; 9] = [0x00, 0xc9, 0x47, 0x24, 0x04, 0x01, 0x00, 0x00, 0x3c];
 pub const SMARTGESTURE_DISABLE_SIGNAL: [u8; 9] = [0x00, 0xc9, 0x47, 0x24, 0x04, 0x00, 0x00, 0x00, 0x57];
 
-const LOAD_VIBRATE_CHARGE_START_SIGNAL: [u8; 9] = [0x00, 0xc9, 0x07, 0x04, 0x04, 0x00, 0x00, 0x00, 0x08];
+pub(crate) const LOAD_VIBRATE_CHARGE_START_SIGNAL: [u8; 9] = [0x00, 0xc9, 0x07, 0x04, 0x04, 0x00, 0x00, 0x00, 0x08];
 
 #[derive(Debug)]
 pub struct NotIlumaError;
 
 impl std::fmt::Display for NotIlumaError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "This device is not an IQOS ILUMA model")
+        write!(f, "{}", tr!("This device is not an IQOS ILUMA model"))
     }
 }
 
 impl std::error::Error for NotIlumaError {}
 
-impl IqosIluma for IqosBle {
+/// Handle for an IQOS ILUMA. Supports the common `Iqos` operations and `IqosIluma`.
+pub struct IlumaDevice {
+    ble: IqosBle,
+}
+
+impl IlumaDevice {
+    pub(crate) fn new(ble: IqosBle) -> Self {
+        Self { ble }
+    }
+
+    pub(crate) fn ble(&self) -> &IqosBle {
+        &self.ble
+    }
+
+    pub(crate) fn ble_mut(&mut self) -> &mut IqosBle {
+        &mut self.ble
+    }
+}
+
+forward_iqos!(IlumaDevice, {
+    fn as_iluma(&self) -> Option<&dyn IqosIluma> {
+        Some(self)
+    }
+});
+
+impl std::fmt::Display for IlumaDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ble)
+    }
+}
+
+#[async_trait]
+impl IqosIluma for IlumaDevice {
+    #[instrument(level = "debug", skip_all)]
     async fn load_iluma_vibration_settings(&self) -> Result<VibrationSettings> {
         let mut when_charge_start: IlumaVibration = IlumaVibration::new(false);
         let mut vibration_settings: VibrationSettings = VibrationSettings::new(
@@ -57,50 +91,30 @@ impl IqosIluma for IqosBle {
             false,
             false,
         );
-        if !self.is_iluma_or_higher() {
-            return Err(IQOSError::IncompatibleModelError);
-        }
-        self.send_command(LOAD_VIBRATE_CHARGE_START_SIGNAL.to_vec()).await?;
-        let mut stream = self.notifications().await?;
-        if let Some(notification) = stream.next().await {
-            if let Ok(when_charge_start) = VibrationSettings::from_bytes_with_charge_start(notification.value.as_slice()) {
-                vibration_settings.iluma_and_higher = Some(when_charge_start);
-            } else {
-                return Err(IQOSError::ConfigurationError("Failed to parse vibration settings".to_string()));
-            }
-            let hex_string = notification.value.iter()
-                .map(|byte| format!("{:02X}", byte))
-                .collect::<Vec<String>>()
-                .join(" ");
-            println!("  Signal: {}", hex_string);
+        let reply = self.ble.query(&LOAD_VIBRATE_CHARGE_START_SIGNAL).await?;
+        if let Ok(when_charge_start) = VibrationSettings::from_bytes_with_charge_start(reply.as_slice()) {
+            vibration_settings.iluma_and_higher = Some(when_charge_start);
+        } else {
+            return Err(IQOSError::ConfigurationError("Failed to parse vibration settings".to_string()));
         }
 
-        self.send_command(LOAD_VIBRATION_SETTINGS_SIGNAL.to_vec()).await?;
-        let mut stream = self.notifications().await?;
-
-        if let Some(notification) = stream.next().await {
-            if let Ok(settings) = VibrationSettings::from_bytes(&notification.value) {
-                vibration_settings.when_heating_start = settings.when_heating_start;
-                vibration_settings.when_starting_to_use = settings.when_starting_to_use;
-                vibration_settings.when_puff_end = settings.when_puff_end;
-                vibration_settings.when_manually_terminated = settings.when_manually_terminated;
-                return Ok(vibration_settings);
-            } else {
-                return Err(IQOSError::ConfigurationError("Failed to parse vibration settings".to_string()));
-            }
+        let reply = self.ble.query(&LOAD_VIBRATION_SETTINGS_SIGNAL).await?;
+        if let Ok(settings) = VibrationSettings::from_bytes(&reply) {
+            vibration_settings.when_heating_start = settings.when_heating_start;
+            vibration_settings.when_starting_to_use = settings.when_starting_to_use;
+            vibration_settings.when_puff_end = settings.when_puff_end;
+            vibration_settings.when_manually_terminated = settings.when_manually_terminated;
+            Ok(vibration_settings)
         } else {
-            return Err(IQOSError::ConfigurationError("No notifications received".to_string()));
+            Err(IQOSError::ConfigurationError("Failed to parse vibration settings".to_string()))
         }
     }
 
+    #[instrument(level = "debug", skip_all)]
     async fn update_iluma_vibration_settings(&self, updates: VibrationSettings) -> Result<()> {
-        if !self.is_iluma_or_higher() {
-            return Err(IQOSError::IncompatibleModelError);
-        }
-
         let current_settings = self.load_iluma_vibration_settings().await?;
         
-        println!("Current settings: {}", current_settings);
+        debug!(?current_settings, "current vibration settings");
         let mut new_settings = VibrationSettings::new(
             updates.when_heating_start.unwrap_or(current_settings.when_heating_start()),
             updates.when_starting_to_use.unwrap_or(current_settings.when_starting_to_use()),
@@ -111,84 +125,53 @@ impl IqosIluma for IqosBle {
         new_settings.iluma_and_higher = Some(updates.iluma_and_higher.unwrap_or(current_settings.iluma_vibration()));
         
         // Generate and send only the necessary signals
-        let signals = IlumaVibrationBehavior::build(&new_settings);
-        for (i, signal) in signals.iter().enumerate() {
-            let hex_string = signal.iter()
-                .map(|byte| format!("{:02X}", byte))
-                .collect::<Vec<String>>()
-                .join(" ");
-            println!("  Signal {}: {}", i, hex_string);
-        }
-        
-        for signal in signals {
-            self.send_command(signal).await?;
+        debug!(?new_settings, "new vibration settings");
+        for signal in IlumaVibrationBehavior::build(&new_settings) {
+            self.ble.send_command(signal).await?;
         }
 
         Ok(())
     }
 
+    #[instrument(level = "debug", skip(self))]
     async fn update_smartgesture(&self, enable: bool) -> Result<()> {
-        if !self.is_iluma_or_higher() {
-            return Err(IQOSError::IncompatibleModelError);
-        }
-
         let signal = if enable {
             SMARTGESTURE_ENABLE_SIGNAL
         } else {
             SMARTGESTURE_DISABLE_SIGNAL
         };
 
-        self.send_command(signal.to_vec()).await?;
+        self.ble.send_command(signal.to_vec()).await?;
 
         Ok(())
     }
 
+    #[instrument(level = "debug", skip(self))]
     async fn update_autostart(&self, enable: bool) -> Result<()> {
-        if !self.is_iluma_or_higher() {
-            return Err(IQOSError::IncompatibleModelError);
-        }
-
         let signal = if enable {
             AUTOSTART_ENABLE_SIGNAL
         } else {
             AUTOSTART_DISABLE_SIGNAL
         };
 
-        self.send_command(signal.to_vec()).await?;
+        self.ble.send_command(signal.to_vec()).await?;
 
         Ok(())
     }
 
+    #[instrument(level = "debug", skip_all)]
     async fn load_flexpuff(&self) -> Result<Flexpuff> {
-        if !self.is_iluma_or_higher() {
-            return Err(IQOSError::IncompatibleModelError);
-        }
-
-        self.send_command(LOAD_FLEXPUFF_SIGNAL.to_vec()).await?;
-        let mut stream = self.notifications().await?;
-
-        if let Some(notification) = stream.next().await {
-            if let Ok(settings) = Flexpuff::from_bytes(&notification.value) {
-                let hex_string = notification.value.iter()
-                    .map(|byte| format!("{:02X}", byte))
-                    .collect::<Vec<String>>()
-                    .join(" ");
-                println!("  Signal: {}", hex_string);
-                return Ok(settings);
-            } else {
-                return Err(IQOSError::ConfigurationError("Failed to parse flexpuff settings".to_string()));
-            }
+        let reply = self.ble.query(&LOAD_FLEXPUFF_SIGNAL).await?;
+        if let Ok(settings) = Flexpuff::from_bytes(&reply) {
+            Ok(settings)
         } else {
-            return Err(IQOSError::ConfigurationError("No notifications received".to_string()));
+            Err(IQOSError::ConfigurationError("Failed to parse flexpuff settings".to_string()))
         }
     }
 
+    #[instrument(level = "debug", skip(self))]
     async fn update_flexpuff(&self, setting: Flexpuff) -> Result<()> {
-        if !self.is_iluma_or_higher() {
-            return Err(IQOSError::IncompatibleModelError);
-        }
-
-        self.send_command(setting.to_bytes()).await?;
+        self.ble.send_command(setting.to_bytes()).await?;
 
         Ok(())
     }
@@ -1,67 +1,106 @@
-use futures::{stream, StreamExt};
+use async_trait::async_trait;
+use tracing::instrument;
 
 use crate::iqos::error::{IQOSError, Result};
 use crate::iqos::flexbattery::{FlexBattery, LOAD_FLEXBATTERY_SIGNAL, LOAD_PAUSEMODE_SIGNAL};
-use super::iqos::IqosBle;
-use super::device::IqosIlumaI;
+use crate::iqos::flexpuff::Flexpuff;
+use crate::iqos::vibration::VibrationSettings;
+use super::iluma::IlumaDevice;
+use super::iqos::{forward_iqos, IqosBle};
+use super::device::{IqosIluma, IqosIlumaI};
 
-impl IqosIlumaI for IqosBle {
+/// Handle for an IQOS ILUMA i. Supports `Iqos`, `IqosIluma` and `IqosIlumaI`.
+pub struct IlumaIDevice {
+    iluma: IlumaDevice,
+}
+
+impl IlumaIDevice {
+    pub(crate) fn new(iluma: IlumaDevice) -> Self {
+        Self { iluma }
+    }
+
+    pub(crate) fn ble(&self) -> &IqosBle {
+        self.iluma.ble()
+    }
+
+    pub(crate) fn ble_mut(&mut self) -> &mut IqosBle {
+        self.iluma.ble_mut()
+    }
+}
+
+forward_iqos!(IlumaIDevice, {
+    fn as_iluma(&self) -> Option<&dyn IqosIluma> {
+        Some(self)
+    }
+
+    fn as_iluma_i(&self) -> Option<&dyn IqosIlumaI> {
+        Some(self)
+    }
+});
+
+impl std::fmt::Display for IlumaIDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ble())
+    }
+}
+
+#[async_trait]
+impl IqosIluma for IlumaIDevice {
+    async fn load_iluma_vibration_settings(&self) -> Result<VibrationSettings> {
+        self.iluma.load_iluma_vibration_settings().await
+    }
+
+    async fn update_iluma_vibration_settings(&self, settings: VibrationSettings) -> Result<()> {
+        self.iluma.update_iluma_vibration_settings(settings).await
+    }
+
+    async fn update_smartgesture(&self, enable: bool) -> Result<()> {
+        self.iluma.update_smartgesture(enable).await
+    }
+
+    async fn update_autostart(&self, enable: bool) -> Result<()> {
+        self.iluma.update_autostart(enable).await
+    }
+
+    async fn load_flexpuff(&self) -> Result<Flexpuff> {
+        self.iluma.load_flexpuff().await
+    }
+
+    async fn update_flexpuff(&self, setting: Flexpuff) -> Result<()> {
+        self.iluma.update_flexpuff(setting).await
+    }
+}
+
+#[async_trait]
+impl IqosIlumaI for IlumaIDevice {
+    #[instrument(level = "debug", skip_all, fields(mode = %new.mode(), pausemode = ?new.is_pausemode()))]
     async fn update_flexbattery(&self, new: FlexBattery) -> Result<()> {
-        self.send_command(new.mode().to_bytes()).await?;
-        let hex_string = new.mode().to_bytes().iter()
-            .map(|byte| format!("{:02X}", byte))
-            .collect::<Vec<String>>()
-            .join(" ");
-        println!("  Signal: {}", hex_string);
+        self.ble().send_command(new.mode().to_bytes()).await?;
         if new.is_performance() {
             if let Some(pausemode) = new.is_pausemode() {
-                self.send_command(FlexBattery::pausemode_to_bytes(pausemode)).await?;
+                self.ble().send_command(FlexBattery::pausemode_to_bytes(pausemode)).await?;
             }
         }
         Ok(())
     }
 
+    #[instrument(level = "debug", skip_all)]
     async fn load_flexbattery(&self) -> Result<FlexBattery> {
-        if !self.is_iluma_i() {
-            return Err(IQOSError::IncompatibleModelError);
-        }
-
         let mut flexbattery: FlexBattery = Default::default();
 
-        self.send_command(LOAD_FLEXBATTERY_SIGNAL.to_vec()).await?;
-        let mut stream = self.notifications().await?;
-        if let Some(notification) = stream.next().await {
-            if let Ok(mode) = FlexBattery::from_bytes(&notification.value) {
-                flexbattery.update_mode(&mode);
-            } else {
-                return Err(IQOSError::ConfigurationError("Invalid flexbattery data received".to_string()))
-            }
-            
-            let hex_string = notification.value.iter()
-                .map(|byte| format!("{:02X}", byte))
-                .collect::<Vec<String>>()
-                .join(" ");
-            println!("  Signal: {}", hex_string);
+        let reply = self.ble().query(&LOAD_FLEXBATTERY_SIGNAL).await?;
+        if let Ok(mode) = FlexBattery::from_bytes(&reply) {
+            flexbattery.update_mode(&mode);
         } else {
-            return Err(IQOSError::ConfigurationError("No notifications received".to_string()));
+            return Err(IQOSError::ConfigurationError("Invalid flexbattery data received".to_string()))
         }
+
         if flexbattery.is_performance() {
-            self.send_command(LOAD_PAUSEMODE_SIGNAL.to_vec()).await?;
-            let mut stream = self.notifications().await?;
-            if let Some(notification) = stream.next().await {
-                if let Ok(pause_mode) = FlexBattery::pausemode_from_bytes(&notification.value) {
-                    flexbattery.update_pause_mode(pause_mode);
-                } else {
-                    return Err(IQOSError::ConfigurationError("Invalid pause mode data received".to_string()));
-                }
-                
-                let hex_string = notification.value.iter()
-                    .map(|byte| format!("{:02X}", byte))
-                    .collect::<Vec<String>>()
-                    .join(" ");
-                println!("  Signal: {}", hex_string);
+            let reply = self.ble().query(&LOAD_PAUSEMODE_SIGNAL).await?;
+            if let Ok(pause_mode) = FlexBattery::pausemode_from_bytes(&reply) {
+                flexbattery.update_pause_mode(pause_mode);
             } else {
-                return Err(IQOSError::ConfigurationError("No notifications received for pause mode".to_string()));
+                return Err(IQOSError::ConfigurationError("Invalid pause mode data received".to_string()));
             }
         }
         Ok(flexbattery)
@@ -1,12 +1,21 @@
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
 use futures::{Stream, StreamExt};
+use tracing::{debug, instrument, trace};
 use super::error::{IQOSError, Result};
-use super::device::Iqos;
-use super::iluma::IlumaSpecific;
-use super::{IqosIluma, COMMAND_CHECKSUM_XOR};
+use crate::i18n::tr;
+use super::device::{DeviceInfo, IqosIluma, IqosIlumaI};
+use super::events::{BatteryLevels, EventSource};
+use super::frame;
+use super::iluma::{IlumaDevice, IlumaSpecific};
+use super::iluma_i::IlumaIDevice;
 use super::brightness::{BrightnessLevel, LOAD_BRIGHTNESS_SIGNAL, BRIGHTNESS_HIGH_SIGNAL, BRIGHTNESS_LOW_SIGNAL};
 use super::vibration::{VibrationBehavior, VibrationSettings, LOAD_VIBRATION_SETTINGS_SIGNAL};
-use btleplug::api::{Characteristic, Peripheral as _, WriteType};
+use btleplug::api::{Characteristic, Peripheral as _, ValueNotification, WriteType};
 use btleplug::platform::Peripheral;
 
 pub const CONFIRMATION_SIGNAL: [u8; 5] = [0x00, 0xc0, 0x01, 0x00, 0xF6];
@@ -25,10 +34,31 @@ pub const UNLOCK_SIGNALS: [&[u8]; 2] = [
 ];
 // pub const UNLOCK_SIGNAL_SECOND: [u8; 5] = [0x00, 0xc9, 0x00, 0x04, 0xC0];
 
-#[derive(Debug, Clone, PartialEq)]
+/// How long `query` waits for the device to answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The next frame on `characteristic` that answers `request`. Battery
+/// updates, device events and replies to other requests are skipped.
+pub(crate) async fn next_reply<S>(stream: &mut S, characteristic: &Characteristic, request: &[u8]) -> Option<Vec<u8>>
+where
+    S: Stream<Item = ValueNotification> + Unpin,
+{
+    while let Some(notification) = stream.next().await {
+        if notification.uuid == characteristic.uuid && frame::is_reply_to(request, &notification.value) {
+            return Some(notification.value);
+        }
+        trace!(frame = %frame::hex(&notification.value), "skipped while waiting for a reply");
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum IQOSModel {
+    #[serde(rename = "ONE")]
     One,
+    #[serde(rename = "ILUMA")]
     Iluma,
+    #[serde(rename = "ILUMA i")]
     IlumaI
 }
 
@@ -44,42 +74,51 @@ impl std::fmt::Display for IQOSModel {
 
 impl IQOSModel {
     pub async fn from_peripheral(peripheral: &Peripheral) -> Self {
-        if let Ok(properties) = peripheral.properties().await {
-            if let Some(properties) = properties {
-                if let Some(name) = properties.local_name {
-                    if name.contains("ONE") {
-                        return IQOSModel::One;
-                    }
-                    else if name.contains("ILUMA i") {
-                        return IQOSModel::IlumaI;
-                    }
-                    else if name.contains("ILUMA") {
-                        return IQOSModel::Iluma;
-                    }
-                }
+        if let Ok(Some(properties)) = peripheral.properties().await {
+            if let Some(name) = properties.local_name {
+                return Self::from_name(&name);
             }
         }
         IQOSModel::Iluma
     }
+
+    /// Guess the model from an advertised name, falling back to ILUMA
+    pub fn from_name(name: &str) -> Self {
+        if name.contains("ONE") {
+            IQOSModel::One
+        } else if name.contains("ILUMA i") {
+            IQOSModel::IlumaI
+        } else {
+            IQOSModel::Iluma
+        }
+    }
 }
 
-pub struct IqosBle {
+/// The BLE transport shared by the typed device handles
+pub(crate) struct IqosBle {
     modelnumber: String,
     serialnumber: String,
     softwarerevision: String,
     manufacturername: String,
     holder_battery_status: u8,
+    charger_battery_status: Option<u8>,
     peripheral: Peripheral,
     battery_characteristic: Characteristic,
     scp_control_characteristic: Characteristic,
     model: IQOSModel,
     product_number: String,
     iluma: Option<IlumaSpecific>,
+    dry_run: AtomicBool,
+    /// The last reply to each read request, for dry runs
+    replies: StdMutex<HashMap<Vec<u8>, Vec<u8>>>,
+    /// What a dry run kept from the device, until taken
+    dry_run_frames: StdMutex<Vec<String>>,
 }
 
 impl IqosBle {
-    pub(crate) async fn new(
+    pub(crate) fn new(
         peripheral: Peripheral,
+        model: IQOSModel,
         modelnumber: String,
         serialnumber: String,
         softwarerevision: String,
@@ -89,7 +128,6 @@ impl IqosBle {
         product_number: String,
         iluma: Option<IlumaSpecific>,
     ) -> Self {
-        let model = IQOSModel::from_peripheral(&peripheral).await;
         Self {
             peripheral,
             modelnumber,
@@ -97,11 +135,15 @@ impl IqosBle {
             softwarerevision,
             manufacturername,
             holder_battery_status: 0,
+            charger_battery_status: None,
             battery_characteristic,
             scp_control_characteristic,
             model,
             product_number,
             iluma,
+            dry_run: AtomicBool::new(false),
+            replies: StdMutex::new(HashMap::new()),
+            dry_run_frames: StdMutex::new(Vec::new()),
         }
     }
 
@@ -113,7 +155,16 @@ impl IqosBle {
         Ok(Box::pin(self.peripheral.notifications().await?))
     }
     
+    /// Write a frame to the SCP control characteristic. In a dry run the
+    /// frame is only recorded, for `take_dry_run_frames`.
     pub async fn send_command(&self, command: Vec<u8>) -> Result<()> {
+        trace!(frame = %frame::hex(&command), command = %frame::describe(&command), "write");
+        if self.dry_run.load(Ordering::Relaxed) {
+            debug!(frame = %frame::hex(&command), "dry run, not sent");
+            self.record_dry_run(tr!("[dry-run] {}: {}", frame::hex(&command), frame::describe_translated(&command)));
+            return Ok(());
+        }
+
         let peripheral = &self.peripheral;
         
         peripheral.write(
@@ -133,151 +184,169 @@ impl IqosBle {
         Ok(())
     }
 
+    /// Send a read request and return the reply. Replies are remembered, so a
+    /// dry run answers with the last real value, or a simulated one.
+    #[instrument(level = "debug", skip_all, fields(request = %frame::describe(request)))]
+    pub async fn query(&self, request: &[u8]) -> Result<Vec<u8>> {
+        if self.dry_run.load(Ordering::Relaxed) {
+            let cached = self.replies.lock().ok().and_then(|replies| replies.get(request).cloned());
+            let reply = cached.or_else(|| frame::simulated_reply(request))
+                .ok_or_else(|| IQOSError::ConfigurationError("No reply is known for this request in a dry run".to_string()))?;
+            debug!(frame = %frame::hex(request), reply = %frame::hex(&reply), "dry run, not sent");
+            self.record_dry_run(tr!("[dry-run] {}: {}, answered with {}", frame::hex(request), frame::describe_translated(request), frame::hex(&reply)));
+            return Ok(reply);
+        }
+
+        let mut stream = self.notifications().await?;
+        self.send_command(request.to_vec()).await?;
+        let reply = tokio::time::timeout(REPLY_TIMEOUT, next_reply(&mut stream, &self.scp_control_characteristic, request)).await
+            .map_err(|_| IQOSError::TimeoutError(tr!("No reply to '{}' within {}", frame::describe_translated(request), format!("{:?}", REPLY_TIMEOUT))))?
+            .ok_or_else(|| IQOSError::ConfigurationError("No notifications received".to_string()))?;
+        trace!(frame = %frame::hex(&reply), "reply");
+        if let Ok(mut replies) = self.replies.lock() {
+            replies.insert(request.to_vec(), reply.clone());
+        }
+        Ok(reply)
+    }
+
     pub async fn send_confirm(&self) -> Result<()> {
         self.send_command(CONFIRMATION_SIGNAL.to_vec()).await?;
         Ok(())
     }
 
-    pub fn as_iluma(&self) -> Option<&IqosBle> {
-        match self.model {
-            IQOSModel::Iluma => Some(self),
-            _ => None,
-        }
+    pub(crate) fn model(&self) -> &IQOSModel {
+        &self.model
     }
 
-    pub fn is_iluma(&self) -> bool {
-        matches!(self.model, IQOSModel::Iluma)
+    pub(crate) fn peripheral(&self) -> &Peripheral {
+        &self.peripheral
     }
 
-    pub fn is_iluma_or_higher(&self) -> bool {
-        matches!(self.model, IQOSModel::Iluma | IQOSModel::IlumaI)
+    pub(crate) fn scp_control_characteristic(&self) -> &Characteristic {
+        &self.scp_control_characteristic
     }
+}
 
-    pub fn is_iluma_i(&self) -> bool {
-        matches!(self.model, IQOSModel::IlumaI)
+/// The `Iqos` operations, implemented on the typed handles by `forward_iqos!`
+impl IqosBle {
+    pub(crate) fn serial_number(&self) -> &str {
+        &self.serialnumber
     }
-    
-    pub fn as_iluma_i(&self) -> Option<&IqosBle> {
-        match self.model {
-            IQOSModel::IlumaI => Some(self),
-            _ => None,
+
+    pub(crate) fn device_info(&self) -> DeviceInfo {
+        DeviceInfo {
+            model: self.model.clone(),
+            model_number: self.modelnumber.clone(),
+            serial_number: self.serialnumber.clone(),
+            software_revision: self.softwarerevision.clone(),
+            manufacturer_name: self.manufacturername.clone(),
+            product_number: self.product_number.clone(),
+            holder_product_number: self.iluma.as_ref().map(|iluma| iluma.holder_product_number().to_string()),
+            battery: self.holder_battery_status,
         }
     }
 
-    pub fn model(&self) -> &IQOSModel {
-        &self.model
+    pub(crate) fn event_source(&self) -> Option<EventSource> {
+        Some(EventSource::new(self.peripheral.clone(), self.battery_characteristic.clone()))
     }
 
-    pub(crate) fn peripheral(&self) -> &Peripheral {
-        &self.peripheral
+    pub(crate) fn set_dry_run(&self, enabled: bool) {
+        self.dry_run.store(enabled, Ordering::Relaxed);
     }
 
-    pub(crate) fn scp_control_characteristic(&self) -> &Characteristic {
-        &self.scp_control_characteristic
+    pub(crate) fn is_dry_run(&self) -> bool {
+        self.dry_run.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn take_dry_run_frames(&self) -> Vec<String> {
+        self.dry_run_frames.lock().map(|mut frames| std::mem::take(&mut *frames)).unwrap_or_default()
     }
-}
 
-impl Iqos for IqosBle {
-    async fn disconnect(&mut self) -> Result<()> {
+    fn record_dry_run(&self, line: String) {
+        if let Ok(mut frames) = self.dry_run_frames.lock() {
+            frames.push(line);
+        }
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn disconnect(&mut self) -> Result<()> {
         self.peripheral.disconnect().await.map_err(IQOSError::BleError)
     }
     
-    async fn reload_battery(&mut self) -> Result<()> {
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn reload_battery(&mut self) -> Result<()> {
         let peripheral = &self.peripheral;
 
         if let Ok(data) = peripheral.read(&self.battery_characteristic)
             .await
             .map_err(IQOSError::BleError) {
-                let battery_status = u8::from_str_radix(&format!("{:02X}", data[2]), 16);
-                self.holder_battery_status = battery_status.unwrap_or(0);
+                if let Some(levels) = BatteryLevels::from_bytes(&data) {
+                    self.holder_battery_status = levels.holder;
+                    self.charger_battery_status = levels.charger;
+                }
             }
         Ok(())
     }
     
-    fn battery_status(&self) -> u8 {
+    pub(crate) fn battery_status(&self) -> u8 {
         self.holder_battery_status
     }
+
+    pub(crate) fn charger_battery_status(&self) -> Option<u8> {
+        self.charger_battery_status
+    }
     
-    async fn vibrate(&self) -> Result<()> {
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn vibrate(&self) -> Result<()> {
         self.send_command(START_VIBRATE_SIGNAL.to_vec()).await?;
         Ok(())
     }
     
-    async fn stop_vibrate(&self) -> Result<()> {
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn stop_vibrate(&self) -> Result<()> {
         self.send_command(STOP_VIBRATE_SIGNAL.to_vec()).await?;
         Ok(())
     }
     
-    async fn lock_device(&self) -> Result<()> {
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn lock_device(&self) -> Result<()> {
         self.send_command_slice(LOCK_SIGNALS).await?;
         self.send_confirm().await?;
         Ok(())
     }
     
-    async fn unlock_device(&self) -> Result<()> {
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn unlock_device(&self) -> Result<()> {
         self.send_command_slice(UNLOCK_SIGNALS).await?;
         self.send_confirm().await?;
         Ok(())
     }
-    async fn load_brightness(&self) -> Result<BrightnessLevel> {
-        self.send_command(LOAD_BRIGHTNESS_SIGNAL.to_vec()).await?;
-        let mut stream = self.notifications().await?;
-
-        if let Some(notification) = stream.next().await {
-            let hex_string = notification.value.iter()
-                .map(|byte| format!("{:02X}", byte))
-                .collect::<Vec<String>>()
-                .join(" ");
-            
-            if let Ok(settings) = BrightnessLevel::from_bytes(&notification.value) {
-                return Ok(settings);
-            } else {
-                return Err(IQOSError::ConfigurationError("Failed to parse brightness settings".to_string()));
-            }
-        } else {
-            return Err(IQOSError::ConfigurationError("No notifications received".to_string()));
-        }
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn load_brightness(&self) -> Result<BrightnessLevel> {
+        let reply = self.query(&LOAD_BRIGHTNESS_SIGNAL).await?;
+        BrightnessLevel::from_bytes(&reply)
+            .map_err(|_| IQOSError::ConfigurationError("Failed to parse brightness settings".to_string()))
     }
 
-    async fn update_brightness(&self, level: BrightnessLevel) -> Result<()> {
+    #[instrument(level = "debug", skip(self))]
+    pub(crate) async fn update_brightness(&self, level: BrightnessLevel) -> Result<()> {
         match level {
             BrightnessLevel::High => self.send_command_slice(BRIGHTNESS_HIGH_SIGNAL).await,
             BrightnessLevel::Low => self.send_command_slice(BRIGHTNESS_LOW_SIGNAL).await,
         }
     }
 
-    async fn load_vibration_settings(&self) -> Result<VibrationSettings> {
-        self.send_command(LOAD_VIBRATION_SETTINGS_SIGNAL.to_vec()).await?;
-        let mut stream = self.notifications().await?;
-
-        if let Some(notification) = stream.next().await {
-            let hex_string = notification.value.iter()
-                .map(|byte| format!("{:02X}", byte))
-                .collect::<Vec<String>>()
-                .join(" ");
-            
-            // if let Some(iluma) = .as_iluma()
-            if let Ok(settings) = VibrationSettings::from_bytes(&notification.value) {
-                return Ok(settings);
-            } else {
-                return Err(IQOSError::ConfigurationError("Failed to parse vibration settings".to_string()));
-            }
-        } else {
-            return Err(IQOSError::ConfigurationError("No notifications received".to_string()));
-        }
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn load_vibration_settings(&self) -> Result<VibrationSettings> {
+        let reply = self.query(&LOAD_VIBRATION_SETTINGS_SIGNAL).await?;
+        VibrationSettings::from_bytes(&reply)
+            .map_err(|_| IQOSError::ConfigurationError("Failed to parse vibration settings".to_string()))
     }
 
-    async fn update_vibration_settings(&self, settings: VibrationSettings) -> Result<()> {
-        let signals = settings.build();
-        for (i, signal) in signals.iter().enumerate() {
-            let hex_string = signal.iter()
-                .map(|byte| format!("{:02X}", byte))
-                .collect::<Vec<String>>()
-                .join(" ");
-            println!("  Signal {}: {}", i, hex_string);
-        }
-        
-        for signal in signals {
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) async fn update_vibration_settings(&self, settings: VibrationSettings) -> Result<()> {
+        debug!(?settings, "new vibration settings");
+        for signal in settings.build() {
             self.send_command(signal).await?;
         }
 
@@ -287,28 +356,182 @@ impl Iqos for IqosBle {
 
 impl std::fmt::Display for IqosBle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.is_iluma_or_higher() {
-            return write!(
-                f,
-                "Model: {}\nModel Number: {}\nSerial Number: {}\nManufacturer Name: {}\n\nStick:\n\tProduct Number: {}\n\tSoftware Revision: {}\nHolder:\n\tHolder Product Number: {}",
-                self.model,
-                self.modelnumber,
-                self.serialnumber,
-                self.manufacturername,
-                self.product_number,
-                self.softwarerevision,
-                self.iluma.as_ref().unwrap().holder_product_number(),
-            )
+        write!(f, "{}", self.device_info())
+    }
+}
+
+/// Implements `Iqos` for a typed handle by forwarding to the `IqosBle` it wraps.
+/// The handle must provide `ble()` and `ble_mut()`. Extra trait items, such as
+/// the `as_iluma` capability accessors, can be passed in braces.
+macro_rules! forward_iqos {
+    ($handle:ty $(, { $($extra:tt)* })?) => {
+        #[async_trait::async_trait]
+        impl $crate::iqos::device::Iqos for $handle {
+            fn model(&self) -> &$crate::iqos::IQOSModel {
+                self.ble().model()
+            }
+
+            fn serial_number(&self) -> &str {
+                self.ble().serial_number()
+            }
+
+            fn device_info(&self) -> $crate::iqos::device::DeviceInfo {
+                self.ble().device_info()
+            }
+
+            fn event_source(&self) -> Option<$crate::iqos::events::EventSource> {
+                self.ble().event_source()
+            }
+
+            fn set_dry_run(&self, enabled: bool) {
+                self.ble().set_dry_run(enabled)
+            }
+
+            fn is_dry_run(&self) -> bool {
+                self.ble().is_dry_run()
+            }
+
+            fn take_dry_run_frames(&self) -> Vec<String> {
+                self.ble().take_dry_run_frames()
+            }
+
+            async fn disconnect(&mut self) -> $crate::iqos::error::Result<()> {
+                self.ble_mut().disconnect().await
+            }
+
+            async fn reload_battery(&mut self) -> $crate::iqos::error::Result<()> {
+                self.ble_mut().reload_battery().await
+            }
+
+            fn battery_status(&self) -> u8 {
+                self.ble().battery_status()
+            }
+
+            fn charger_battery_status(&self) -> Option<u8> {
+                self.ble().charger_battery_status()
+            }
+
+            async fn vibrate(&self) -> $crate::iqos::error::Result<()> {
+                self.ble().vibrate().await
+            }
+
+            async fn stop_vibrate(&self) -> $crate::iqos::error::Result<()> {
+                self.ble().stop_vibrate().await
+            }
+
+            async fn lock_device(&self) -> $crate::iqos::error::Result<()> {
+                self.ble().lock_device().await
+            }
+
+            async fn unlock_device(&self) -> $crate::iqos::error::Result<()> {
+                self.ble().unlock_device().await
+            }
+
+            async fn load_brightness(&self) -> $crate::iqos::error::Result<$crate::iqos::BrightnessLevel> {
+                self.ble().load_brightness().await
+            }
+
+            async fn update_brightness(&self, level: $crate::iqos::BrightnessLevel) -> $crate::iqos::error::Result<()> {
+                self.ble().update_brightness(level).await
+            }
+
+            async fn load_vibration_settings(&self) -> $crate::iqos::error::Result<$crate::iqos::VibrationSettings> {
+                self.ble().load_vibration_settings().await
+            }
+
+            async fn update_vibration_settings(&self, settings: $crate::iqos::VibrationSettings) -> $crate::iqos::error::Result<()> {
+                self.ble().update_vibration_settings(settings).await
+            }
+
+            $($($extra)*)?
+        }
+    };
+}
+
+pub(crate) use forward_iqos;
+
+/// Handle for an IQOS ONE. Only the common `Iqos` operations are available.
+pub struct OneDevice {
+    ble: IqosBle,
+}
+
+impl OneDevice {
+    pub(crate) fn new(ble: IqosBle) -> Self {
+        Self { ble }
+    }
+
+    pub(crate) fn ble(&self) -> &IqosBle {
+        &self.ble
+    }
+
+    pub(crate) fn ble_mut(&mut self) -> &mut IqosBle {
+        &mut self.ble
+    }
+}
+
+forward_iqos!(OneDevice);
+
+/// A connected IQOS device, typed by model.
+///
+/// Each variant only implements the traits its model supports, so calling an
+/// ILUMA i feature on an ONE is a compile error when the handle is matched out.
+pub enum IqosDevice {
+    One(OneDevice),
+    Iluma(IlumaDevice),
+    IlumaI(IlumaIDevice),
+}
+
+impl IqosDevice {
+    pub(crate) fn from_ble(ble: IqosBle) -> Self {
+        match ble.model() {
+            IQOSModel::One => IqosDevice::One(OneDevice::new(ble)),
+            IQOSModel::Iluma => IqosDevice::Iluma(IlumaDevice::new(ble)),
+            IQOSModel::IlumaI => IqosDevice::IlumaI(IlumaIDevice::new(IlumaDevice::new(ble))),
+        }
+    }
+
+    pub(crate) fn ble(&self) -> &IqosBle {
+        match self {
+            IqosDevice::One(device) => device.ble(),
+            IqosDevice::Iluma(device) => device.ble(),
+            IqosDevice::IlumaI(device) => device.ble(),
         }
-        write!(
-            f,
-            "Model: {}\nModel Number: {}\nSerial Number: {}\nSoftware Revision: {}\nManufacturer Name: {}\nProduct Number: {}",
-            self.model,
-            self.modelnumber,
-            self.serialnumber,
-            self.softwarerevision,
-            self.manufacturername,
-            self.product_number,
-        )
-    }
-}
\ No newline at end of file
+    }
+
+    pub(crate) fn ble_mut(&mut self) -> &mut IqosBle {
+        match self {
+            IqosDevice::One(device) => device.ble_mut(),
+            IqosDevice::Iluma(device) => device.ble_mut(),
+            IqosDevice::IlumaI(device) => device.ble_mut(),
+        }
+    }
+}
+
+forward_iqos!(IqosDevice, {
+    fn as_iluma(&self) -> Option<&dyn IqosIluma> {
+        match self {
+            IqosDevice::Iluma(device) => Some(device),
+            IqosDevice::IlumaI(device) => Some(device),
+            IqosDevice::One(_) => None,
+        }
+    }
+
+    fn as_iluma_i(&self) -> Option<&dyn IqosIlumaI> {
+        match self {
+            IqosDevice::IlumaI(device) => Some(device),
+            _ => None,
+        }
+    }
+});
+
+impl std::fmt::Display for OneDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ble)
+    }
+}
+
+impl std::fmt::Display for IqosDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ble())
+    }
+}
@@ -5,37 +5,37 @@ mod tests {
 
     #[test]
     fn test_parse_from_bytes_valid() {
-        // テストケース1: すべての設定がオフのデータ
+        // Case 1: every setting off
         let data1 = [0x00, 0x08, 0x84, 0x23, 0x10, 0x00, 0x00, 0x00, 0x00, 0x77];
-        let settings = VibrationSettings::parse_from_bytes(&data1).expect("有効なデータを解析できませんでした");
+        let settings = VibrationSettings::parse_from_bytes(&data1).expect("failed to parse valid data");
         assert!(!settings.when_charging_start);
         assert!(!settings.when_heating_start);
         assert!(!settings.when_starting_to_use);
         assert!(!settings.when_puff_end);
         assert!(!settings.when_manually_terminated);
 
-        // テストケース2: 加熱開始とパフエンドがオンのデータ
+        // Case 2: heating start and puff end on
         let data2 = [0x00, 0x08, 0x84, 0x23, 0x10, 0x00, 0x00, 0x01, 0x01, 0x77];
-        let settings = VibrationSettings::parse_from_bytes(&data2).expect("有効なデータを解析できませんでした");
+        let settings = VibrationSettings::parse_from_bytes(&data2).expect("failed to parse valid data");
         assert!(!settings.when_charging_start);
         assert!(settings.when_heating_start);
         assert!(!settings.when_starting_to_use);
         assert!(settings.when_puff_end);
         assert!(!settings.when_manually_terminated);
 
-        // テストケース3: 使用開始と手動終了がオンのデータ
+        // Case 3: starting to use and manual termination on
         let data3 = [0x00, 0x08, 0x84, 0x23, 0x10, 0x00, 0x00, 0x10, 0x10, 0x77];
-        let settings = VibrationSettings::parse_from_bytes(&data3).expect("有効なデータを解析できませんでした");
+        let settings = VibrationSettings::parse_from_bytes(&data3).expect("failed to parse valid data");
         assert!(!settings.when_charging_start);
         assert!(!settings.when_heating_start);
         assert!(settings.when_starting_to_use);
         assert!(!settings.when_puff_end);
         assert!(settings.when_manually_terminated);
 
-        // テストケース4: すべての設定がオンのデータ
+        // Case 4: every setting on
         let data4 = [0x00, 0x08, 0x84, 0x23, 0x10, 0x00, 0x00, 0x11, 0x11, 0x77];
-        let settings = VibrationSettings::parse_from_bytes(&data4).expect("有効なデータを解析できませんでした");
-        assert!(!settings.when_charging_start); // charge設定は通知パケットに含まれていない
+        let settings = VibrationSettings::parse_from_bytes(&data4).expect("failed to parse valid data");
+        assert!(!settings.when_charging_start); // the charging setting is not part of the notification
         assert!(settings.when_heating_start);
         assert!(settings.when_starting_to_use);
         assert!(settings.when_puff_end);
@@ -44,7 +44,7 @@ mod tests {
 
     #[test]
     fn test_parse_from_bytes_invalid() {
-        // テストケース1: データ長が短すぎる
+        // Case 1: data too short
         let data1 = [0x00, 0x08, 0x84, 0x23, 0x10];
         let result = VibrationSettings::parse_from_bytes(&data1);
         assert!(result.is_err());
@@ -52,10 +52,10 @@ mod tests {
             Err(IQOSError::ConfigurationError(msg)) => {
                 assert!(msg.contains("Data too short"));
             }
-            _ => panic!("期待するエラー種別ではありません"),
+            _ => panic!("unexpected error kind"),
         }
 
-        // テストケース2: ヘッダーが不正
+        // Case 2: invalid header
         let data2 = [0x01, 0x08, 0x84, 0x23, 0x10, 0x00, 0x00, 0x00, 0x00, 0x77];
         let result = VibrationSettings::parse_from_bytes(&data2);
         assert!(result.is_err());
@@ -63,10 +63,10 @@ mod tests {
             Err(IQOSError::ConfigurationError(msg)) => {
                 assert!(msg.contains("Invalid header"));
             }
-            _ => panic!("期待するエラー種別ではありません"),
+            _ => panic!("unexpected error kind"),
         }
 
-        // テストケース3: 異なるコマンド型
+        // Case 3: a different command type
         let data3 = [0x00, 0x08, 0x84, 0x24, 0x10, 0x00, 0x00, 0x00, 0x00, 0x77];
         let result = VibrationSettings::parse_from_bytes(&data3);
         assert!(result.is_err());
@@ -74,7 +74,7 @@ mod tests {
             Err(IQOSError::ConfigurationError(msg)) => {
                 assert!(msg.contains("Invalid header"));
             }
-            _ => panic!("期待するエラー種別ではありません"),
+            _ => panic!("unexpected error kind"),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,225 @@
+//! An in-memory device for tests of code built on the `Iqos` traits
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use super::device::{DeviceInfo, Iqos, IqosIluma, IqosIlumaI};
+use super::error::{IQOSError, Result};
+use super::flexbattery::FlexBattery;
+use super::{BrightnessLevel, Flexpuff, IQOSModel, VibrationSettings};
+
+/// Remembers what was written and answers loads with it. Device calls can
+/// be made to fail with `fail_with`, for tests of error handling. In a dry
+/// run, brightness updates are recorded instead of applied.
+pub struct MockIqos {
+    pub info: DeviceInfo,
+    pub brightness: Mutex<BrightnessLevel>,
+    pub vibration: Mutex<VibrationSettings>,
+    pub flexpuff: Mutex<Flexpuff>,
+    /// Serialized, as `FlexBattery` is not `Clone`
+    pub flexbattery: Mutex<serde_json::Value>,
+    pub locked: Mutex<bool>,
+    pub autostart: Mutex<bool>,
+    pub smartgesture: Mutex<bool>,
+    dry_run: AtomicBool,
+    dry_run_frames: Mutex<Vec<String>>,
+    failures: Mutex<HashMap<&'static str, IQOSError>>,
+}
+
+impl MockIqos {
+    pub fn new(model: IQOSModel, serial_number: &str, battery: u8) -> Self {
+        let holder_product_number = (model != IQOSModel::One).then(|| "HOLDER".to_string());
+        Self {
+            info: DeviceInfo {
+                model,
+                model_number: "MODEL".to_string(),
+                serial_number: serial_number.to_string(),
+                software_revision: "1.0".to_string(),
+                manufacturer_name: "Mock".to_string(),
+                product_number: "PRODUCT".to_string(),
+                holder_product_number,
+                battery,
+            },
+            brightness: Mutex::new(BrightnessLevel::High),
+            vibration: Mutex::new(VibrationSettings::new(true, false, true, false)),
+            flexpuff: Mutex::new(Flexpuff::new(false)),
+            flexbattery: Mutex::new(serde_json::to_value(FlexBattery::default()).expect("flexbattery")),
+            locked: Mutex::new(false),
+            autostart: Mutex::new(false),
+            smartgesture: Mutex::new(false),
+            dry_run: AtomicBool::new(false),
+            dry_run_frames: Mutex::new(Vec::new()),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Make the next call to `method`, e.g. `"update_smartgesture"`, fail
+    /// with `error`
+    pub fn fail_with(&self, method: &'static str, error: IQOSError) {
+        self.failures.lock().unwrap().insert(method, error);
+    }
+
+    fn failure(&self, method: &str) -> Result<()> {
+        match self.failures.lock().unwrap().remove(method) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for MockIqos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.info)
+    }
+}
+
+#[async_trait]
+impl Iqos for MockIqos {
+    fn model(&self) -> &IQOSModel {
+        &self.info.model
+    }
+
+    fn serial_number(&self) -> &str {
+        &self.info.serial_number
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        self.info.clone()
+    }
+
+    fn as_iluma(&self) -> Option<&dyn IqosIluma> {
+        (self.info.model != IQOSModel::One).then_some(self as &dyn IqosIluma)
+    }
+
+    fn as_iluma_i(&self) -> Option<&dyn IqosIlumaI> {
+        (self.info.model == IQOSModel::IlumaI).then_some(self as &dyn IqosIlumaI)
+    }
+
+    fn set_dry_run(&self, enabled: bool) {
+        self.dry_run.store(enabled, Ordering::Relaxed);
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run.load(Ordering::Relaxed)
+    }
+
+    fn take_dry_run_frames(&self) -> Vec<String> {
+        std::mem::take(&mut *self.dry_run_frames.lock().unwrap())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.failure("disconnect")?;
+        Ok(())
+    }
+
+    async fn reload_battery(&mut self) -> Result<()> {
+        self.failure("reload_battery")?;
+        Ok(())
+    }
+
+    fn battery_status(&self) -> u8 {
+        self.info.battery
+    }
+
+    async fn vibrate(&self) -> Result<()> {
+        self.failure("vibrate")?;
+        Ok(())
+    }
+
+    async fn stop_vibrate(&self) -> Result<()> {
+        self.failure("stop_vibrate")?;
+        Ok(())
+    }
+
+    async fn lock_device(&self) -> Result<()> {
+        self.failure("lock_device")?;
+        *self.locked.lock().unwrap() = true;
+        Ok(())
+    }
+
+    async fn unlock_device(&self) -> Result<()> {
+        self.failure("unlock_device")?;
+        *self.locked.lock().unwrap() = false;
+        Ok(())
+    }
+
+    async fn load_brightness(&self) -> Result<BrightnessLevel> {
+        self.failure("load_brightness")?;
+        Ok(*self.brightness.lock().unwrap())
+    }
+
+    async fn update_brightness(&self, level: BrightnessLevel) -> Result<()> {
+        self.failure("update_brightness")?;
+        if self.is_dry_run() {
+            self.dry_run_frames.lock().unwrap().push(format!("set brightness to {:?}", level));
+        } else {
+            *self.brightness.lock().unwrap() = level;
+        }
+        Ok(())
+    }
+
+    async fn load_vibration_settings(&self) -> Result<VibrationSettings> {
+        self.failure("load_vibration_settings")?;
+        Ok(self.vibration.lock().unwrap().clone())
+    }
+
+    async fn update_vibration_settings(&self, settings: VibrationSettings) -> Result<()> {
+        self.failure("update_vibration_settings")?;
+        *self.vibration.lock().unwrap() = settings;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IqosIluma for MockIqos {
+    async fn load_iluma_vibration_settings(&self) -> Result<VibrationSettings> {
+        self.failure("load_iluma_vibration_settings")?;
+        self.load_vibration_settings().await
+    }
+
+    async fn update_iluma_vibration_settings(&self, settings: VibrationSettings) -> Result<()> {
+        self.failure("update_iluma_vibration_settings")?;
+        self.update_vibration_settings(settings).await
+    }
+
+    async fn update_smartgesture(&self, enable: bool) -> Result<()> {
+        self.failure("update_smartgesture")?;
+        *self.smartgesture.lock().unwrap() = enable;
+        Ok(())
+    }
+
+    async fn update_autostart(&self, enable: bool) -> Result<()> {
+        self.failure("update_autostart")?;
+        *self.autostart.lock().unwrap() = enable;
+        Ok(())
+    }
+
+    async fn load_flexpuff(&self) -> Result<Flexpuff> {
+        self.failure("load_flexpuff")?;
+        Ok(*self.flexpuff.lock().unwrap())
+    }
+
+    async fn update_flexpuff(&self, setting: Flexpuff) -> Result<()> {
+        self.failure("update_flexpuff")?;
+        *self.flexpuff.lock().unwrap() = setting;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IqosIlumaI for MockIqos {
+    async fn update_flexbattery(&self, new: FlexBattery) -> Result<()> {
+        self.failure("update_flexbattery")?;
+        *self.flexbattery.lock().unwrap() = serde_json::to_value(new).expect("flexbattery");
+        Ok(())
+    }
+
+    async fn load_flexbattery(&self) -> Result<FlexBattery> {
+        self.failure("load_flexbattery")?;
+        serde_json::from_value(self.flexbattery.lock().unwrap().clone())
+            .map_err(|e| IQOSError::ConfigurationError(e.to_string()))
+    }
+}
@@ -1,25 +1,35 @@
+pub mod adapter;
 mod builder;
 mod iqos;
 pub mod iluma;
 pub mod iluma_i;
 pub mod error;
 pub mod device;
+pub mod events;
+pub mod frame;
 pub mod flexbattery;
 pub mod brightness;
 pub mod vibration;
 pub mod flexpuff;
+pub mod scanner;
 
 use uuid::{uuid, Uuid};
 
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+pub(crate) mod mock;
 
-pub use builder::IQOSBuilder;
-pub use iqos::IqosBle;
-pub use device::{Iqos, IqosIluma, IqosIlumaI};
+pub use builder::{BuilderOptions, IQOSBuilder};
+pub use iqos::{IqosDevice, OneDevice, IQOSModel};
+pub use iluma::IlumaDevice;
+pub use iluma_i::IlumaIDevice;
+pub use device::{DeviceInfo, Iqos, IqosIluma, IqosIlumaI, SharedIqos};
+pub use events::DeviceEvent;
 pub use brightness::BrightnessLevel;
 pub use vibration::VibrationSettings;
 pub use flexpuff::Flexpuff;
+pub use scanner::{DiscoveredIqos, IqosScanner};
 
 // Service UUIDs
 pub const DEVICE_INFO_SERVICE_UUID: Uuid = uuid!("0000180a-0000-1000-8000-00805f9b34fb");
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::time::Duration;
+
+use btleplug::api::{Central, CentralEvent, Peripheral as _, ScanFilter};
+use btleplug::platform::{Adapter, Peripheral, PeripheralId};
+use futures::{Stream, StreamExt};
+
+use super::error::{IQOSError, Result};
+use super::iqos::IQOSModel;
+use super::CORE_SERVICE_UUID;
+
+/// An IQOS device seen during a scan
+#[derive(Debug, Clone)]
+pub struct DiscoveredIqos {
+    pub id: PeripheralId,
+    pub name: String,
+    pub rssi: Option<i16>,
+    pub guessed_model: IQOSModel,
+    peripheral: Peripheral,
+}
+
+impl DiscoveredIqos {
+    pub fn peripheral(&self) -> &Peripheral {
+        &self.peripheral
+    }
+
+    pub fn into_peripheral(self) -> Peripheral {
+        self.peripheral
+    }
+}
+
+/// Scans an adapter for IQOS devices.
+///
+/// A peripheral counts as an IQOS when it advertises `CORE_SERVICE_UUID`, or
+/// when its name contains "IQOS" (not every model advertises the service).
+/// Each device is yielded when it is first recognised, and again whenever its
+/// name or signal strength changes, so consumers should replace earlier
+/// entries with the same id.
+pub struct IqosScanner {
+    adapter: Adapter,
+    timeout: Option<Duration>,
+}
+
+impl IqosScanner {
+    pub fn new(adapter: Adapter) -> Self {
+        Self { adapter, timeout: None }
+    }
+
+    /// Stop the scan stream after the given duration
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn adapter(&self) -> &Adapter {
+        &self.adapter
+    }
+
+    /// Start scanning and return a stream of discovered devices.
+    ///
+    /// The stream ends when the timeout elapses, if one is set. Call `stop`
+    /// once a device has been chosen.
+    pub async fn scan(&self) -> Result<Pin<Box<dyn Stream<Item = DiscoveredIqos> + Send>>> {
+        let events = self.adapter.events().await?;
+        self.adapter.start_scan(ScanFilter::default()).await?;
+
+        // Last name and signal strength reported for each device
+        let state = (events, self.adapter.clone(), HashMap::<PeripheralId, (String, Option<i16>)>::new());
+        let stream = futures::stream::unfold(state, |(mut events, adapter, mut seen)| async move {
+            while let Some(event) = events.next().await {
+                let id = match event {
+                    CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => id,
+                    _ => continue,
+                };
+                let Some(found) = Self::recognise(&adapter, &id).await else {
+                    continue;
+                };
+                let current = (found.name.clone(), found.rssi);
+                if seen.get(&id) == Some(&current) {
+                    continue;
+                }
+                seen.insert(id, current);
+                return Some((found, (events, adapter, seen)));
+            }
+            None
+        });
+
+        match self.timeout {
+            Some(timeout) => Ok(Box::pin(stream.take_until(tokio::time::sleep(timeout)))),
+            None => Ok(Box::pin(stream)),
+        }
+    }
+
+    /// Scan until the timeout elapses and return every device found, strongest signal first
+    pub async fn discover_all(&self) -> Result<Vec<DiscoveredIqos>> {
+        if self.timeout.is_none() {
+            return Err(IQOSError::ConfigurationError("A scan timeout is required to collect all devices".to_string()));
+        }
+
+        let mut latest: HashMap<PeripheralId, DiscoveredIqos> = HashMap::new();
+        let mut devices = self.scan().await?;
+        while let Some(device) = devices.next().await {
+            latest.insert(device.id.clone(), device);
+        }
+        self.stop().await?;
+
+        let mut found: Vec<DiscoveredIqos> = latest.into_values().collect();
+        found.sort_by(|a, b| b.rssi.cmp(&a.rssi));
+        Ok(found)
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        self.adapter.stop_scan().await?;
+        Ok(())
+    }
+
+    async fn recognise(adapter: &Adapter, id: &PeripheralId) -> Option<DiscoveredIqos> {
+        let peripheral = adapter.peripheral(id).await.ok()?;
+        let properties = peripheral.properties().await.ok()??;
+        let name = properties.local_name.unwrap_or_default();
+
+        if !properties.services.contains(&CORE_SERVICE_UUID) && !name.contains("IQOS") {
+            return None;
+        }
+
+        Some(DiscoveredIqos {
+            id: id.clone(),
+            guessed_model: IQOSModel::from_name(&name),
+            name,
+            rssi: properties.rssi,
+            peripheral,
+        })
+    }
+}
@@ -3,70 +3,67 @@ mod tests {
     use btleplug::api::{Central, Manager as _, Peripheral as _, ScanFilter, CentralEvent};
     use btleplug::platform::Manager;
     use futures::stream::StreamExt;
-    use crate::iqos::builder::IQOSBuilder;
+    use crate::iqos::builder::{Connected, IQOSBuilder};
     use crate::iqos::error::{Result, IQOSError};
 
-    async fn discover_and_print_services(iqos: &mut IQOSBuilder) -> Result<()> {
+    async fn discover_and_print_services(iqos: IQOSBuilder<Connected>) -> Result<()> {
         println!("Connected! Discovering services...");
-        let services = iqos.discover_services().await?;
+        let iqos = iqos.discover_services().await?;
+        let services = iqos.services();
         
-        println!("発見されたサービス:");
+        println!("Discovered services:");
         if services.is_empty() {
-            println!("  サービスが見つかりませんでした");
+            println!("  No services found");
         } else {
             for (i, service) in services.iter().enumerate() {
-                println!("  サービス #{}: {}", i + 1, service.uuid);
-                println!("    プライマリ: {}", service.primary);
+                println!("  Service #{}: {}", i + 1, service.uuid);
+                println!("    Primary: {}", service.primary);
                 
                 if !service.characteristics.is_empty() {
-                    println!("    特性:");
+                    println!("    Characteristics:");
                     for (j, characteristic) in service.characteristics.iter().enumerate() {
-                        println!("      特性 #{}.{}: {}", i + 1, j + 1, characteristic.uuid);
-                        println!("        プロパティ: {:?}", characteristic.properties);
+                        println!("      Characteristic #{}.{}: {}", i + 1, j + 1, characteristic.uuid);
+                        println!("        Properties: {:?}", characteristic.properties);
                         
-                        // Device Information Serviceの場合、characteristic UUIDを判別
+                        // Name the standard characteristics of the Device Information Service
                         if service.uuid.to_string() == "0000180a-0000-1000-8000-00805f9b34fb" {
                             let uuid_string = characteristic.uuid.to_string();
                             let uuid_short = uuid_string.split('-').next().unwrap_or("");
                             match uuid_short {
-                                "00002a24" => println!("        標準特性: Model Number String"),
-                                "00002a25" => println!("        標準特性: Serial Number String"),
-                                "00002a28" => println!("        標準特性: Software Revision String"),
-                                "00002a29" => println!("        標準特性: Manufacturer Name String"),
-                                _ => println!("        標準特性: 未定義"),
+                                "00002a24" => println!("        Standard characteristic: Model Number String"),
+                                "00002a25" => println!("        Standard characteristic: Serial Number String"),
+                                "00002a28" => println!("        Standard characteristic: Software Revision String"),
+                                "00002a29" => println!("        Standard characteristic: Manufacturer Name String"),
+                                _ => println!("        Standard characteristic: unknown"),
                             }
                         }
                         
-                        // 読み取り可能な場合は値を読み取って表示
+                        // Read and show the value of readable characteristics
                         if characteristic.properties.contains(btleplug::api::CharPropFlags::READ) {
-                            print!("        読み取り中...");
-                            if let Ok(p) = iqos.peripheral() {
-                                match p.read(&characteristic).await {
-                                    Ok(data) => {
-                                        if let Ok(text) = String::from_utf8(data.clone()) {
-                                            if text.chars().all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace()) {
-                                                println!("値 (文字列): {}", text);
-                                            } else {
-                                                println!("値 (ASCII): {}", data.iter()
-                                                    .map(|&b| b.to_ascii_lowercase() as char)
-                                                    .collect::<String>());
-                                            }
+                            print!("        Reading...");
+                            match iqos.peripheral().read(characteristic).await {
+                                Ok(data) => {
+                                    if let Ok(text) = String::from_utf8(data.clone()) {
+                                        if text.chars().all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace()) {
+                                            println!("Value (string): {}", text);
                                         } else {
-                                            println!("値 (16進数): {}", data.iter()
-                                                .map(|b| format!("{:02X}", b))
-                                                .collect::<Vec<_>>()
-                                                .join(" "));
+                                            println!("Value (ASCII): {}", data.iter()
+                                                .map(|&b| b.to_ascii_lowercase() as char)
+                                                .collect::<String>());
                                         }
-                                    },
-                                    Err(e) => println!("読み取りエラー: {}", e)
-                                }
-                            } else {
-                                println!("peripheralの取得に失敗");
+                                    } else {
+                                        println!("Value (hex): {}", data.iter()
+                                            .map(|b| format!("{:02X}", b))
+                                            .collect::<Vec<_>>()
+                                            .join(" "));
+                                    }
+                                },
+                                Err(e) => println!("Read error: {}", e)
                             }
                         }
                     }
                 } else {
-                    println!("    特性: なし");
+                    println!("    Characteristics: none");
                 }
                 println!();
             }
@@ -89,9 +86,8 @@ mod tests {
                 let properties = peripheral.properties().await.map_err(IQOSError::from)?;
                 if let Some(name) = properties.and_then(|p| p.local_name) {
                     if name.contains("IQOS") {
-                        let mut iqos = IQOSBuilder::new(peripheral);
-                        iqos.connect().await?;
-                        discover_and_print_services(&mut iqos).await?;
+                        let iqos = IQOSBuilder::new(peripheral).connect().await?;
+                        discover_and_print_services(iqos).await?;
                         central.stop_scan().await.map_err(IQOSError::from)?;
                         break;
                     }
@@ -102,6 +98,6 @@ mod tests {
     }
 }
 
-// チェックサムテスト用のモジュールは別ファイルで定義済み
+// The checksum tests live in their own file
 #[cfg(test)]
 mod iqos_checksum_tests;
\ No newline at end of file
@@ -0,0 +1,64 @@
+use crate::iqos::frame;
+use crate::iqos::vibration::{VibrationBehavior, VibrationSettings};
+
+#[test]
+fn test_vibration_checksum() {
+    let settings = VibrationSettings::new(false, false, false, false);
+
+    // Every option off is 0x77
+    assert_eq!(settings.checksum(&0x0000), 0x77);
+    // Each option bit XORs in its own value
+    assert_eq!(settings.checksum(&0x0001), 0x77 ^ 0x07);
+    assert_eq!(settings.checksum(&0x0010), 0x77 ^ 0x70);
+    assert_eq!(settings.checksum(&0x0100), 0x77 ^ 0x15);
+    assert_eq!(settings.checksum(&0x1000), 0x77 ^ 0x57);
+    // 0x77 ^ 0x07 ^ 0x70 ^ 0x15 ^ 0x57
+    assert_eq!(settings.checksum(&0x1111), 0x42);
+}
+
+#[test]
+fn test_vibration_frame_ends_with_checksum() {
+    let settings = VibrationSettings::new(true, false, true, false);
+    let frames = VibrationBehavior::build(&settings);
+
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0], vec![0x00, 0xC9, 0x44, 0x23, 0x10, 0x00, 0x01, 0x01, 0x77 ^ 0x15 ^ 0x07]);
+}
+
+#[test]
+fn test_describe_vibration_frame() {
+    let settings = VibrationSettings::new(true, false, true, false);
+    let frames = VibrationBehavior::build(&settings);
+
+    assert_eq!(
+        frame::describe(&frames[0]),
+        "set vibration: heating on, starting off, puff end on, terminated off"
+    );
+}
+
+#[test]
+fn test_describe_known_and_unknown_frames() {
+    assert_eq!(frame::describe(&[0x00, 0xc0, 0x01, 0x00, 0xF6]), "confirm the previous command");
+    assert_eq!(frame::describe(&[0x00, 0xc0, 0x02, 0x23, 0xC3]), "read brightness");
+    assert_eq!(frame::describe(&[0x01, 0x02, 0x03]), "unknown frame");
+    assert_eq!(frame::hex(&[0x00, 0xC9, 0x0a]), "00 C9 0A");
+}
+
+#[test]
+fn test_simulated_replies_parse() {
+    use crate::iqos::brightness::{BrightnessLevel, LOAD_BRIGHTNESS_SIGNAL};
+    use crate::iqos::flexpuff::{Flexpuff, LOAD_FLEXPUFF_SIGNAL};
+    use crate::iqos::vibration::LOAD_VIBRATION_SETTINGS_SIGNAL;
+
+    let reply = frame::simulated_reply(&LOAD_BRIGHTNESS_SIGNAL).expect("brightness reply");
+    assert!(BrightnessLevel::from_bytes(&reply).is_ok());
+
+    let reply = frame::simulated_reply(&LOAD_VIBRATION_SETTINGS_SIGNAL).expect("vibration reply");
+    let settings = <VibrationSettings as VibrationBehavior>::from_bytes(&reply).expect("vibration settings");
+    assert!(settings.when_heating_start() && settings.when_puff_end());
+
+    let reply = frame::simulated_reply(&LOAD_FLEXPUFF_SIGNAL).expect("flexpuff reply");
+    assert!(Flexpuff::from_bytes(&reply).is_ok());
+
+    assert!(frame::simulated_reply(&[0x01, 0x02]).is_none());
+}
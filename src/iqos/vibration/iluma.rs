@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::iqos::error::{IQOSError, Result};
 use super::variant::{VibrationBehavior, IlumaVibrationBehavior};
 use super::settings::{VibrationSettings, WHEN_HEATING_START_SIGNAL, WHEN_STARTING_TO_USE_SIGNAL, WHEN_PUFF_END_SIGNAL, WHEN_MANUALLY_TERMINATED_SIGNAL};
@@ -27,7 +29,7 @@ pub const WHEN_CHARGING_START_OFF_SIGNALS: [&[u8]; 7] = [
 const WHEN_CHARGE_START_ON_SIGNAL: [u8; 19] = [0x00, 0x08, 0x8B, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x56];
 const WHEN_CHARGE_START_OFF_SIGNAL: [u8; 19] = [0x00, 0x08, 0x8B, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xEE];
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct IlumaVibration {
     pub when_charging_start: bool,
 }
@@ -96,7 +98,6 @@ impl IlumaVibrationBehavior for VibrationSettings {
             );
         }
         
-        println!("  Signal: {:?}", ret);
         ret
     }
 
@@ -8,4 +8,6 @@ pub use variant::IlumaVibrationBehavior;
 
 pub use settings::VibrationSettings;
 pub use settings::LOAD_VIBRATION_SETTINGS_SIGNAL;
+pub use settings::{WHEN_HEATING_START_SIGNAL, WHEN_STARTING_TO_USE_SIGNAL, WHEN_PUFF_END_SIGNAL, WHEN_MANUALLY_TERMINATED_SIGNAL};
+pub use iluma::{WHEN_CHARGING_START_ON_SIGNALS, WHEN_CHARGING_START_OFF_SIGNALS};
 pub use iluma::IlumaVibration;
\ No newline at end of file
@@ -1,3 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+
 use super::variant::IlumaVibrationBehavior;
 use super::iluma::IlumaVibration;
 
@@ -8,12 +12,21 @@ pub const WHEN_HEATING_START_SIGNAL: u16 = 0x0100;
 pub const WHEN_MANUALLY_TERMINATED_SIGNAL: u16 = 0x0010;
 pub const WHEN_PUFF_END_SIGNAL: u16 = 0x0001;
 
-#[derive(Debug, Clone)]
+/// Vibration settings. `None` fields are left unchanged on update.
+///
+/// Serialized flat, so ILUMA settings simply carry an extra
+/// `when_charging_start` field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VibrationSettings {
+    #[serde(flatten)]
     pub iluma_and_higher: Option<IlumaVibration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub when_heating_start: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub when_starting_to_use: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub when_puff_end: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub when_manually_terminated: Option<bool>,
 }
 
@@ -81,22 +94,13 @@ impl VibrationSettings {
 
 impl std::fmt::Display for VibrationSettings {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "\n{}", tr!("Vibration Settings"))?;
         if self.is_iluma() {
-            let charging_start = self.when_charging_start();
-            write!(f, "\nVibration Settings\n\twhen charge start: {}\n\twhen heating: {}\n\twhen starting: {}\n\twhen puff end soon: {}\n\twhen terminated: {}\n",
-                charging_start,
-                self.when_heating_start(),
-                self.when_starting_to_use(),
-                self.when_puff_end(),
-                self.when_manually_terminated(),
-            )
-        } else {
-            write!(f, "\nVibration Settings\n\twhen heating: {}\n\twhen starting: {}\n\twhen puff end soon: {}\n\twhen terminated: {}\n",
-                self.when_heating_start(),
-                self.when_starting_to_use(),
-                self.when_puff_end(),
-                self.when_manually_terminated(),
-            )
+            writeln!(f, "\t{}", tr!("when charge start: {}", self.when_charging_start()))?;
         }
+        writeln!(f, "\t{}", tr!("when heating: {}", self.when_heating_start()))?;
+        writeln!(f, "\t{}", tr!("when starting: {}", self.when_starting_to_use()))?;
+        writeln!(f, "\t{}", tr!("when puff end soon: {}", self.when_puff_end()))?;
+        writeln!(f, "\t{}", tr!("when terminated: {}", self.when_manually_terminated()))
     }
 }
\ No newline at end of file
@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+
+use anyhow::Result;
+
+use crate::i18n::tr;
+use tokio::sync::Mutex;
+
+use crate::iqos::SharedIqos;
+use crate::loader::parser::IQOSConsole;
+
+use super::command::{CommandError, CommandInfo, CommandRegistry};
+
+/// A user defined command that runs other commands in order
+struct Alias {
+    definition: String,
+    steps: Vec<String>,
+    params: usize,
+}
+
+impl Alias {
+    fn parse(definition: &str) -> Self {
+        let steps: Vec<String> = definition.split(';')
+            .map(|step| step.trim().to_string())
+            .filter(|step| !step.is_empty())
+            .collect();
+        let params = steps.iter()
+            .flat_map(|step| step.split_whitespace())
+            .filter_map(|word| word.strip_prefix('$')?.parse::<usize>().ok())
+            .max()
+            .unwrap_or(0);
+        Self { definition: definition.trim().to_string(), steps, params }
+    }
+
+    /// The commands each step names
+    fn commands(&self) -> impl Iterator<Item = String> + '_ {
+        self.steps.iter().filter_map(|step| step.split_whitespace().next()).map(str::to_lowercase)
+    }
+
+    /// Substitute `$1`..`$9` and `$*` in each step
+    fn expand(&self, args: &[String]) -> Vec<Vec<String>> {
+        self.steps.iter()
+            .map(|step| step.split_whitespace()
+                .flat_map(|word| match word {
+                    "$*" => args.to_vec(),
+                    _ => match word.strip_prefix('$').and_then(|n| n.parse::<usize>().ok()) {
+                        Some(n) if n >= 1 => args.get(n - 1).cloned().into_iter().collect(),
+                        _ => vec![word.to_string()],
+                    },
+                })
+                .collect())
+            .collect()
+    }
+}
+
+/// Register the aliases from the config. Aliases may not shadow a command or
+/// refer back to themselves.
+pub async fn register_aliases(console: &IQOSConsole, aliases: &BTreeMap<String, String>) {
+    let parsed: BTreeMap<String, Arc<Alias>> = aliases.iter()
+        .map(|(name, definition)| (name.to_lowercase(), Arc::new(Alias::parse(definition))))
+        .collect();
+    let registry = console.registry();
+
+    for (name, alias) in &parsed {
+        if console.list_commands().await.contains(name) {
+            eprintln!("{}", tr!("Ignoring alias '{}': a command with this name exists", name));
+            continue;
+        }
+        if is_recursive(name, &parsed, &mut HashSet::new()) {
+            eprintln!("{}", tr!("Ignoring alias '{}': it refers to itself", name));
+            continue;
+        }
+
+        let alias = alias.clone();
+        let usage = std::iter::once(name.clone())
+            .chain((1..=alias.params).map(|n| format!("<arg{}>", n)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let info = CommandInfo::new(
+            intern(name.clone()),
+            intern(tr!("Alias for: {}", alias.definition)),
+            intern(tr!("Usage: {}", usage)),
+            false,
+            false,
+        );
+
+        let registry = registry.clone();
+        console.register_command(info, Box::new(move |iqos, args| {
+            let alias = alias.clone();
+            let registry = registry.clone();
+            Box::pin(async move {
+                execute_alias(&alias, registry, iqos, args).await
+            })
+        })).await;
+    }
+}
+
+async fn execute_alias(alias: &Alias, registry: Arc<Mutex<CommandRegistry>>, iqos: SharedIqos, args: Vec<String>) -> Result<()> {
+    let params = &args[1..];
+    if params.len() < alias.params {
+        return Err(CommandError::Usage(tr!("{} expects {} argument(s)", args[0], alias.params)).into());
+    }
+
+    for step in alias.expand(params) {
+        let Some(command) = step.first().map(|s| s.to_lowercase()) else {
+            continue;
+        };
+        let future = {
+            let commands = registry.lock().await;
+            let cmd = commands.get(&command)
+                .ok_or_else(|| CommandError::UnknownCommand(command.clone()))?;
+            (cmd.run)(iqos.clone(), step)
+        };
+        future.await?;
+    }
+    Ok(())
+}
+
+fn is_recursive(name: &str, aliases: &BTreeMap<String, Arc<Alias>>, visiting: &mut HashSet<String>) -> bool {
+    if !visiting.insert(name.to_string()) {
+        return true;
+    }
+    let recursive = aliases.get(name).is_some_and(|alias| {
+        alias.commands().any(|command| aliases.contains_key(&command) && is_recursive(&command, aliases, visiting))
+    });
+    visiting.remove(name);
+    recursive
+}
+
+/// A `'static` copy of `s`, shared by every registration of the same text,
+/// so re-registering the aliases does not grow memory
+fn intern(s: String) -> &'static str {
+    static INTERNED: OnceLock<StdMutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut interned = INTERNED.get_or_init(Default::default).lock().unwrap();
+    if let Some(existing) = interned.get(s.as_str()) {
+        return existing;
+    }
+    let leaked: &'static str = Box::leak(s.into_boxed_str());
+    interned.insert(leaked);
+    leaked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iqos::mock::MockIqos;
+    use crate::iqos::IQOSModel;
+    use crate::loader::cmds::command::RegisteredCommand;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn aliases(definitions: &[(&str, &str)]) -> BTreeMap<String, Arc<Alias>> {
+        definitions.iter()
+            .map(|(name, definition)| (name.to_string(), Arc::new(Alias::parse(definition))))
+            .collect()
+    }
+
+    type Calls = Arc<StdMutex<Vec<Vec<String>>>>;
+
+    /// A registry whose commands record the arguments they were run with
+    fn recording_registry(names: &[&'static str]) -> (Arc<Mutex<CommandRegistry>>, Calls) {
+        let calls = Arc::new(StdMutex::new(Vec::new()));
+        let mut registry = CommandRegistry::new();
+        for name in names {
+            let recorded = calls.clone();
+            let info = CommandInfo::new(name, "", "", false, false);
+            registry.insert(name.to_string(), RegisteredCommand::new(info, Box::new(move |_, args| {
+                recorded.lock().unwrap().push(args);
+                Box::pin(async { Ok(()) })
+            })));
+        }
+        (Arc::new(Mutex::new(registry)), calls)
+    }
+
+    fn mock() -> SharedIqos {
+        Arc::new(Mutex::new(MockIqos::new(IQOSModel::Iluma, "SERIAL", 80)))
+    }
+
+    #[test]
+    fn parses_steps_and_parameters() {
+        let alias = Alias::parse(" brightness $1 ;; vibration charge $2 ; battery ");
+        assert_eq!(alias.definition, "brightness $1 ;; vibration charge $2 ; battery");
+        assert_eq!(alias.steps, ["brightness $1", "vibration charge $2", "battery"]);
+        assert_eq!(alias.params, 2);
+        assert_eq!(alias.commands().collect::<Vec<_>>(), ["brightness", "vibration", "battery"]);
+    }
+
+    #[test]
+    fn star_is_not_a_numbered_parameter() {
+        assert_eq!(Alias::parse("flexbattery $*").params, 0);
+    }
+
+    #[test]
+    fn expands_numbered_and_all_arguments() {
+        let alias = Alias::parse("brightness $2; flexbattery $*; lock");
+        let expanded = alias.expand(&args("eco low"));
+        assert_eq!(expanded, [args("brightness low"), args("flexbattery eco low"), args("lock")]);
+    }
+
+    #[test]
+    fn drops_missing_arguments() {
+        let alias = Alias::parse("vibration $1 $3");
+        assert_eq!(alias.expand(&args("on")), [args("vibration on")]);
+    }
+
+    #[test]
+    fn detects_self_recursion() {
+        let aliases = aliases(&[("loop", "battery; loop")]);
+        assert!(is_recursive("loop", &aliases, &mut HashSet::new()));
+    }
+
+    #[test]
+    fn detects_mutual_recursion() {
+        let aliases = aliases(&[("ping", "pong"), ("pong", "battery; PING")]);
+        assert!(is_recursive("ping", &aliases, &mut HashSet::new()));
+        assert!(is_recursive("pong", &aliases, &mut HashSet::new()));
+    }
+
+    #[test]
+    fn allows_aliases_sharing_a_step() {
+        let aliases = aliases(&[("night", "dim; lock"), ("bed", "dim; battery"), ("dim", "brightness low")]);
+        assert!(!is_recursive("night", &aliases, &mut HashSet::new()));
+        assert!(!is_recursive("bed", &aliases, &mut HashSet::new()));
+    }
+
+    #[tokio::test]
+    async fn runs_each_step_with_expanded_arguments() {
+        let (registry, calls) = recording_registry(&["brightness", "lock"]);
+        let alias = Alias::parse("brightness $1; LOCK");
+        execute_alias(&alias, registry, mock(), args("night low")).await.unwrap();
+        assert_eq!(*calls.lock().unwrap(), [args("brightness low"), args("LOCK")]);
+    }
+
+    #[tokio::test]
+    async fn rejects_too_few_arguments() {
+        let (registry, calls) = recording_registry(&["vibration"]);
+        let alias = Alias::parse("vibration $1 $2");
+        let error = execute_alias(&alias, registry, mock(), args("buzz on")).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<CommandError>(), Some(CommandError::Usage(_))));
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stops_at_an_unknown_command() {
+        let (registry, calls) = recording_registry(&["battery"]);
+        let alias = Alias::parse("missing; battery");
+        let error = execute_alias(&alias, registry, mock(), args("broken")).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<CommandError>(), Some(CommandError::UnknownCommand(name)) if name == "missing"));
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn interns_equal_strings_once() {
+        let first = intern("Usage: night <arg1>".to_string());
+        let second = intern("Usage: night <arg1>".to_string());
+        assert!(std::ptr::eq(first, second));
+    }
+}
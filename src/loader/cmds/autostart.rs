@@ -1,14 +1,13 @@
-use std::sync::Arc;
 use anyhow::Result;
-use tokio::sync::Mutex;
 
-use crate::iqos::IqosBle;
+use crate::i18n::{tr, translate};
+
+use crate::iqos::SharedIqos;
 use crate::iqos::IqosIluma;
-use crate::iqos::IqosIlumaI;
-use crate::iqos::flexbattery::FlexBattery;
+use crate::loader::output;
 use crate::loader::parser::IQOSConsole;
 
-use super::command::{CommandRegistry, CommandInfo};
+use super::command::{CommandError, CommandInfo, Capabilities};
 
 pub fn command_info() -> CommandInfo {
     CommandInfo::new(
@@ -18,38 +17,40 @@ pub fn command_info() -> CommandInfo {
         true,  // Requires ILUMA model
         false, // Does not require ILUMA-i model
     )
+    .with_completer(complete_args)
+}
+
+/// Complete `on|off`
+fn complete_args(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    match args.len() {
+        0 => vec!["on", "off"],
+        _ => vec![],
+    }
 }
 
 pub async fn register_command(console: &IQOSConsole) {
-    console.register_command("autostart", Box::new(|iqos, args| {
+    console.register_command(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
     })).await;
 }
 
-pub async fn execute_command(iqos: Arc<Mutex<IqosBle>>, args: Vec<String>) -> Result<()> {
+pub async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
     let iqos = iqos.lock().await;
-    if let Some(arg) = args.get(1) {
-        match arg.to_lowercase() {
-            s if s == "on" || s == "enable" => {
-                let result = IqosIluma::update_autostart(&*iqos, true).await;
-                match result {
-                    Ok(_) => println!("Autostart enabled"),
-                    Err(e) => println!("Error: {}", e),
-                }
-            },
-            s if s == "off" || s == "disable" => {
-                let result = IqosIluma::update_autostart(&*iqos, false).await;
-                match result {
-                    Ok(_) => println!("Autostart disabled"),
-                    Err(e) => println!("Error: {}", e),
-                }
-            },
-            _ => println!("Usage: autostart [on|off]"),
-        }
-    } else {
-        println!("Usage: autostart [on|off]");
+    let Some(iluma) = iqos.as_iluma() else {
+        return Err(CommandError::unsupported_model("ILUMA").into());
+    };
+    match args.get(1).map(|s| s.to_lowercase()).as_deref() {
+        Some("on") | Some("enable") => {
+            IqosIluma::update_autostart(iluma, true).await?;
+            output::message(tr!("Autostart enabled"));
+        },
+        Some("off") | Some("disable") => {
+            IqosIluma::update_autostart(iluma, false).await?;
+            output::message(tr!("Autostart disabled"));
+        },
+        _ => return Err(CommandError::Usage(translate(command_info().usage).to_string()).into()),
     }
     Ok(())
 }
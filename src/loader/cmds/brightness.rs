@@ -1,13 +1,15 @@
-use std::sync::Arc;
 use anyhow::Result;
-use tokio::sync::Mutex;
 
-use crate::iqos::IqosBle;
+use crate::i18n::{tr, translate};
+use serde_json::json;
+
+use crate::iqos::SharedIqos;
 use crate::iqos::device::Iqos;
 use crate::iqos::brightness::BrightnessLevel;
+use crate::loader::output;
 use crate::loader::parser::IQOSConsole;
 
-use super::command::{CommandRegistry, CommandInfo};
+use super::command::{CommandError, CommandRegistry, CommandInfo, RegisteredCommand, Capabilities};
 
 /// Get information about the brightness command
 pub fn command_info() -> CommandInfo {
@@ -18,11 +20,20 @@ pub fn command_info() -> CommandInfo {
         true,   // Requires ILUMA model
         false,  // Does not require ILUMA-i model
     )
+    .with_completer(complete_args)
+}
+
+/// Complete `high|low`
+fn complete_args(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    match args.len() {
+        0 => vec!["high", "low"],
+        _ => vec![],
+    }
 }
 
 /// Register the brightness command
 pub async fn register_command(console: &IQOSConsole) {
-    console.register_command("brightness", Box::new(|iqos, args| {
+    console.register_command(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
@@ -31,35 +42,33 @@ pub async fn register_command(console: &IQOSConsole) {
 
 /// Register the brightness command using the registry directly
 pub fn register(commands: &mut CommandRegistry) {
-    commands.insert("brightness".to_string(), Box::new(|iqos, args| {
+    commands.insert("brightness".to_string(), RegisteredCommand::new(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
-    }));
+    })));
 }
 
 /// Execute the brightness command
-async fn execute_command(iqos: Arc<Mutex<IqosBle>>, args: Vec<String>) -> Result<()> {
+async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
     let iqos = iqos.lock().await;
 
+    if iqos.as_iluma().is_none() {
+        return Err(CommandError::unsupported_model("ILUMA").into());
+    }
+
     match args.get(1).map(|s| s.parse::<BrightnessLevel>()) {
         Some(Ok(level)) => {
-            if iqos.is_iluma_or_higher() {
-                // Explicitly call the Iqos trait method
-                Iqos::update_brightness(&*iqos, level).await?;
-                println!("Set brightness to {}", level);
-            } else {
-                println!("This device is not an ILUMA model");
-            }
+            // Explicitly call the Iqos trait method
+            Iqos::update_brightness(&*iqos, level).await?;
+            output::print(&json!({ "brightness": level }), tr!("Set brightness to {}", level));
         },
-        Some(Err(e)) => println!("{}", e),
+        Some(Err(_)) => return Err(CommandError::Usage(translate(command_info().usage).to_string()).into()),
         None => {
             // Explicitly call the Iqos trait method
-            match Iqos::load_brightness(&*iqos).await {
-                Ok(level) => println!("{}", level),
-                Err(_) => println!("This device is not an ILUMA model")
-            }
+            let level = Iqos::load_brightness(&*iqos).await?;
+            output::print(&json!({ "brightness": level }), level);
         },
     }
     Ok(())
-}
\ No newline at end of file
+}
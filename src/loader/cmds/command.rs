@@ -1,34 +1,114 @@
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
 
 use anyhow::Result;
-use tokio::sync::Mutex;
 
-use crate::iqos::IqosBle;
+use crate::i18n::tr;
+
+use crate::iqos::SharedIqos;
+use crate::iqos::device::Iqos;
+use crate::iqos::error::IQOSError;
 
 /// Command function type - represents a function that can be executed as a CLI command
-pub type CommandFn = Box<dyn Fn(Arc<Mutex<IqosBle>>, Vec<String>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+pub type CommandFn = Box<dyn Fn(SharedIqos, Vec<String>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+
+/// A command and the metadata used for help and completion
+pub struct RegisteredCommand {
+    pub info: CommandInfo,
+    pub run: CommandFn,
+}
+
+impl RegisteredCommand {
+    pub fn new(info: CommandInfo, run: CommandFn) -> Self {
+        Self { info, run }
+    }
+}
 
 /// Type alias for command registry
-pub type CommandRegistry = HashMap<String, CommandFn>;
+pub type CommandRegistry = HashMap<String, RegisteredCommand>;
 
 /// Command registration function signature
 pub type CommandRegistrationFn = fn(&mut CommandRegistry);
 
+/// Errors raised by commands that callers classify, e.g. to pick an exit status
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("{}", tr!("Unknown command: {}", .0))]
+    UnknownCommand(String),
+    /// Invalid arguments; the message is the usage text
+    #[error("{0}")]
+    Usage(String),
+    /// The connected model does not support the command
+    #[error("{0}")]
+    Unsupported(String),
+    /// No device could be found or connected to
+    #[error("{0}")]
+    Connection(String),
+}
+
+impl CommandError {
+    pub fn unsupported_model(required: &str) -> Self {
+        CommandError::Unsupported(tr!("This device is not an {} model", required))
+    }
+}
+
+/// Exit status for the one-shot CLI
+///
+/// 1: the command failed, 2: invalid usage, 3: not supported by this model,
+/// 4: device not found or Bluetooth error
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    if let Some(err) = err.downcast_ref::<CommandError>() {
+        return match err {
+            CommandError::UnknownCommand(_) | CommandError::Usage(_) => 2,
+            CommandError::Unsupported(_) => 3,
+            CommandError::Connection(_) => 4,
+        };
+    }
+    if err.downcast_ref::<btleplug::Error>().is_some() {
+        return 4;
+    }
+    match err.downcast_ref::<IQOSError>() {
+        Some(IQOSError::BleError(_)) | Some(IQOSError::AdapterError(_)) | Some(IQOSError::TimeoutError(_)) => 4,
+        Some(IQOSError::IncompatibleModelError) | Some(IQOSError::NotIluma(_)) => 3,
+        _ => 1,
+    }
+}
+
+/// What the connected device supports, used to filter help and completion
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capabilities {
+    pub iluma: bool,
+    pub iluma_i: bool,
+}
+
+impl Capabilities {
+    pub fn of(iqos: &dyn Iqos) -> Self {
+        Self {
+            iluma: iqos.as_iluma().is_some(),
+            iluma_i: iqos.as_iluma_i().is_some(),
+        }
+    }
+}
+
+/// Suggests values for the next argument, given the arguments typed so far
+/// (without the command name)
+pub type ArgCompleter = fn(&[&str], Capabilities) -> Vec<&'static str>;
+
 /// Basic description of a command - useful for help text and documentation
+#[derive(Debug, Clone)]
 pub struct CommandInfo {
     pub name: &'static str,
     pub description: &'static str,
     pub usage: &'static str,
     pub requires_iluma: bool,
     pub requires_iluma_i: bool,
+    pub complete_args: Option<ArgCompleter>,
 }
 
 impl CommandInfo {
     /// Create a new command info
-    pub fn new(
+    pub const fn new(
         name: &'static str,
         description: &'static str,
         usage: &'static str,
@@ -41,9 +121,41 @@ impl CommandInfo {
             usage,
             requires_iluma,
             requires_iluma_i,
+            complete_args: None,
         }
     }
+
+    /// Attach a completer for the command's arguments
+    pub const fn with_completer(mut self, completer: ArgCompleter) -> Self {
+        self.complete_args = Some(completer);
+        self
+    }
+
+    /// Suggestions for the next argument
+    pub fn complete(&self, args: &[&str], capabilities: Capabilities) -> Vec<&'static str> {
+        self.complete_args.map_or_else(Vec::new, |complete| complete(args, capabilities))
+    }
     
+    /// Whether the connected device has the features this command needs
+    pub fn is_supported_by(&self, iqos: &dyn Iqos) -> bool {
+        self.is_supported(Capabilities::of(iqos))
+    }
+
+    pub fn is_supported(&self, capabilities: Capabilities) -> bool {
+        (!self.requires_iluma || capabilities.iluma) && (!self.requires_iluma_i || capabilities.iluma_i)
+    }
+
+    /// The model this command needs, if any
+    pub fn required_model(&self) -> Option<&'static str> {
+        if self.requires_iluma_i {
+            Some("ILUMA i")
+        } else if self.requires_iluma {
+            Some("ILUMA")
+        } else {
+            None
+        }
+    }
+
     /// Create command info from tuple format for backward compatibility
     pub fn from_tuple(info: (&'static str, &'static str, &'static str, bool)) -> Self {
         let (name, description, usage, requires_iluma) = info;
@@ -53,6 +165,7 @@ impl CommandInfo {
             usage,
             requires_iluma,
             requires_iluma_i: false,
+            complete_args: None,
         }
     }
 }
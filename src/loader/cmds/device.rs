@@ -0,0 +1,98 @@
+use anyhow::Result;
+
+use crate::i18n::{tr, translate};
+
+use crate::iqos::SharedIqos;
+use crate::loader::known_devices::KnownDevices;
+use crate::loader::output::{self, OutputFormat};
+use crate::loader::parser::IQOSConsole;
+
+use super::command::{CommandError, CommandInfo, Capabilities};
+
+/// Get information about the device command
+pub fn command_info() -> CommandInfo {
+    CommandInfo::new(
+        "device",
+        "Manage known devices",
+        "Usage: device [list|name <alias>|unname|prefer|unprefer|forget]",
+        false, // Does not require ILUMA model
+        false, // Does not require ILUMA-i model
+    )
+    .with_completer(complete_args)
+}
+
+/// Complete the subcommand
+fn complete_args(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    match args.len() {
+        0 => vec!["list", "name", "unname", "prefer", "unprefer", "forget"],
+        _ => vec![],
+    }
+}
+
+/// Register the device command
+pub async fn register_command(console: &IQOSConsole) {
+    console.register_command(command_info(), Box::new(|iqos, args| {
+        Box::pin(async move {
+            execute_command(iqos, args).await
+        })
+    })).await;
+}
+
+/// Execute the device command
+async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
+    let serial = iqos.lock().await.serial_number().to_string();
+    let mut known = KnownDevices::load();
+
+    match args.get(1).map(|s| s.as_str()) {
+        None | Some("list") if output::format() == OutputFormat::Json => {
+            output::print(known.devices(), "");
+        },
+        None | Some("list") => {
+            if known.devices().is_empty() {
+                output::message(tr!("No known devices"));
+            }
+            for device in known.devices() {
+                let current = if device.serial == serial { "*" } else { " " };
+                let preferred = if known.is_preferred(&device.serial) { tr!(" (preferred)") } else { "" };
+                println!(
+                    "{} {:<16} {:<8} {} {}{}",
+                    current,
+                    device.alias.as_deref().unwrap_or("-"),
+                    device.model.to_string(),
+                    device.serial,
+                    device.peripheral_id,
+                    preferred,
+                );
+            }
+        },
+        Some("name") => match args.get(2) {
+            Some(alias) => {
+                known.set_alias(&serial, Some(alias.clone()))?;
+                output::message(tr!("This device is now known as '{}'", alias));
+            },
+            None => return Err(CommandError::Usage(tr!("Usage: device name <alias>").to_string()).into()),
+        },
+        Some("unname") => {
+            known.set_alias(&serial, None)?;
+            output::message(tr!("Alias removed"));
+        },
+        Some("prefer") => {
+            known.set_preferred(Some(&serial))?;
+            output::message(tr!("This device will be connected automatically at startup"));
+        },
+        Some("unprefer") => {
+            known.set_preferred(None)?;
+            output::message(tr!("No device will be connected automatically at startup"));
+        },
+        Some("forget") => {
+            if !output::confirm(tr!("Forget this device? Its alias and preference are removed")) {
+                output::message(tr!("Cancelled"));
+                return Ok(());
+            }
+            known.forget(&serial)?;
+            output::message(tr!("This device has been removed from the known devices"));
+        },
+        Some(opt) => return Err(CommandError::Usage(tr!("Invalid option: {}. {}", opt, translate(command_info().usage))).into()),
+    }
+    Ok(())
+}
@@ -1,28 +1,40 @@
-use std::sync::Arc;
 use anyhow::Result;
-use tokio::sync::Mutex;
 
-use crate::iqos::IqosBle;
-use crate::iqos::IqosIlumaI;
+use crate::i18n::{tr, translate};
+
+use crate::iqos::SharedIqos;
 use crate::iqos::flexbattery::FlexBattery;
+use crate::loader::output;
 use crate::loader::parser::IQOSConsole;
 
-use super::command::{CommandRegistry, CommandInfo};
+use super::command::{CommandError, CommandRegistry, CommandInfo, RegisteredCommand, Capabilities};
 
 /// Get information about the flexbattery command
 pub fn command_info() -> CommandInfo {
     CommandInfo::new(
         "flexbattery",
         "Configure FlexBattery feature",
-        "Usage: flexbattery [performance|eco] | pause [on|off]",
+        "Usage: flexbattery [performance|eco] [pausemode on|off]",
         true,  // Requires ILUMA model
         true,  // Requires ILUMA-i model
     )
+    .with_completer(complete_args)
+}
+
+/// Complete modes, and `on|off` after `pausemode`
+fn complete_args(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    match args.last() {
+        Some(&"pausemode") => vec!["on", "off"],
+        _ => ["eco", "performance", "pausemode"]
+            .into_iter()
+            .filter(|option| !args.contains(option))
+            .collect(),
+    }
 }
 
 /// Register the flexbattery command
 pub async fn register_command(console: &IQOSConsole) {
-    console.register_command("flexbattery", Box::new(|iqos, args| {
+    console.register_command(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
@@ -31,38 +43,31 @@ pub async fn register_command(console: &IQOSConsole) {
 
 /// Register the flexbattery command using the registry directly
 pub fn register(commands: &mut CommandRegistry) {
-    commands.insert("flexbattery".to_string(), Box::new(|iqos, args| {
+    commands.insert("flexbattery".to_string(), RegisteredCommand::new(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
-    }));
+    })));
 }
 
 /// Execute the flexbattery command
-async fn execute_command(iqos: Arc<Mutex<IqosBle>>, args: Vec<String>) -> Result<()> {
+async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
     let iqos = iqos.lock().await;
     let str_args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
 
+    let Some(iluma_i) = iqos.as_iluma_i() else {
+        return Err(CommandError::unsupported_model("ILUMA i").into());
+    };
+
     if args.len() == 1 {
         // No arguments provided, show current flexbattery mode
-        if let Some(iluma_i) = iqos.as_iluma_i() {
-            match iluma_i.load_flexbattery().await {
-                Ok(flexbattery) => println!("\n{}\n", flexbattery),
-                Err(e) => println!("Error: {}", e),
-            }
-        } else {
-            println!("This device is not an ILUMA i model");
-        }
-    } else if args.len() >= 2 {
-        if let Some(iluma_i) = iqos.as_iluma_i() {
-            let fb = FlexBattery::from_args(&str_args[1..])?;
-            iluma_i.update_flexbattery(fb).await?;
-            println!("Flexbattery mode updated.");
-        } else {
-            println!("This device is not an ILUMA i model");
-        }
+        let flexbattery = iluma_i.load_flexbattery().await?;
+        output::print(&flexbattery, format!("\n{}\n", flexbattery));
     } else {
-        println!("Usage: flexbattery [performance|eco] | pause [on|off]");
+        let fb = FlexBattery::from_args(&str_args[1..])
+            .map_err(|e| CommandError::Usage(format!("{}\n{}", e, translate(command_info().usage))))?;
+        iluma_i.update_flexbattery(fb).await?;
+        output::message(tr!("Flexbattery mode updated."));
     }
     Ok(())
 }
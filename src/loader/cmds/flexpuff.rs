@@ -1,13 +1,14 @@
-use std::sync::Arc;
 use anyhow::Result;
-use tokio::sync::Mutex;
 
-use crate::iqos::IqosBle;
+use crate::i18n::{tr, translate};
+
+use crate::iqos::SharedIqos;
 use crate::iqos::device::IqosIluma;
 use crate::iqos::flexpuff::Flexpuff;
+use crate::loader::output;
 use crate::loader::parser::IQOSConsole;
 
-use super::command::{CommandRegistry, CommandInfo};
+use super::command::{CommandError, CommandRegistry, CommandInfo, RegisteredCommand, Capabilities};
 
 /// Get information about the flexpuff command
 pub fn command_info() -> CommandInfo {
@@ -18,11 +19,20 @@ pub fn command_info() -> CommandInfo {
         true,  // Requires ILUMA model
         false, // Does not require ILUMA-i model
     )
+    .with_completer(complete_args)
+}
+
+/// Complete `status|enable|disable`
+fn complete_args(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    match args.len() {
+        0 => vec!["status", "enable", "disable"],
+        _ => vec![],
+    }
 }
 
 /// Register the flexpuff command
 pub async fn register_command(console: &IQOSConsole) {
-    console.register_command("flexpuff", Box::new(|iqos, args| {
+    console.register_command(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
@@ -31,74 +41,48 @@ pub async fn register_command(console: &IQOSConsole) {
 
 /// Register the flexpuff command using the registry directly
 pub fn register(commands: &mut CommandRegistry) {
-    commands.insert("flexpuff".to_string(), Box::new(|iqos, args| {
+    commands.insert("flexpuff".to_string(), RegisteredCommand::new(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
-    }));
+    })));
 }
 
 /// Execute the flexpuff command
-async fn execute_command(iqos: Arc<Mutex<IqosBle>>, args: Vec<String>) -> Result<()> {
+async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
     let iqos = iqos.lock().await;
     
     // Check if device is ILUMA
-    if !iqos.is_iluma_or_higher() {
-        println!("FlexPuff is only available on ILUMA devices.");
-        return Ok(());
-    }
+    let Some(iluma) = iqos.as_iluma() else {
+        return Err(CommandError::Unsupported(tr!("FlexPuff is only available on ILUMA devices.").to_string()).into());
+    };
     
     match args.get(1).map(|s| s.as_str()) {
-        Some("status") => handle_status(&iqos).await,
-        Some("enable") => handle_enable(&iqos).await,
-        Some("disable") => handle_disable(&iqos).await,
-        Some(opt) => {
-            println!("Invalid option: {}. Please specify 'enable' or 'disable'", opt);
-            Ok(())
-        },
-        None => {
-            println!("Usage: flexpuff [status|enable|disable]");
-            Ok(())
-        }
+        Some("status") => handle_status(iluma).await,
+        Some("enable") => handle_enable(iluma).await,
+        Some("disable") => handle_disable(iluma).await,
+        Some(opt) => Err(CommandError::Usage(tr!("Invalid option: {}. Please specify 'enable' or 'disable'", opt)).into()),
+        None => Err(CommandError::Usage(translate(command_info().usage).to_string()).into()),
     }
 }
 
 /// Handle the status subcommand
-async fn handle_status(iqos: &IqosBle) -> Result<()> {
-    // Need to handle this as an IqosIluma trait
-    if let Some(iluma) = iqos.as_iluma() {
-        let status = iluma.load_flexpuff().await?;
-        println!("\nFlexpuff status: {}\n", status);
-    } else {
-        println!("This device is not an ILUMA model");
-    }
+async fn handle_status(iluma: &dyn IqosIluma) -> Result<()> {
+    let status = iluma.load_flexpuff().await?;
+    output::print(&status, format!("\n{}\n", tr!("Flexpuff status: {}", status)));
     Ok(())
 }
 
 /// Handle the enable subcommand
-async fn handle_enable(iqos: &IqosBle) -> Result<()> {
-    let flexpuff = Flexpuff::new(true);
-    if let Some(iluma) = iqos.as_iluma() {
-        match iluma.update_flexpuff(flexpuff).await {
-            Ok(_) => println!("Flexpuff enabled"),
-            Err(e) => println!("Error: {}", e),
-        }
-    } else {
-        println!("This device is not an ILUMA model");
-    }
+async fn handle_enable(iluma: &dyn IqosIluma) -> Result<()> {
+    iluma.update_flexpuff(Flexpuff::new(true)).await?;
+    output::message(tr!("Flexpuff enabled"));
     Ok(())
 }
 
 /// Handle the disable subcommand
-async fn handle_disable(iqos: &IqosBle) -> Result<()> {
-    let flexpuff = Flexpuff::new(false);
-    if let Some(iluma) = iqos.as_iluma() {
-        match iluma.update_flexpuff(flexpuff).await {
-            Ok(_) => println!("Flexpuff disabled"),
-            Err(e) => println!("Error: {}", e),
-        }
-    } else {
-        println!("This device is not an ILUMA model");
-    }
+async fn handle_disable(iluma: &dyn IqosIluma) -> Result<()> {
+    iluma.update_flexpuff(Flexpuff::new(false)).await?;
+    output::message(tr!("Flexpuff disabled"));
     Ok(())
 }
@@ -5,5 +5,8 @@ pub mod brightness;
 pub mod vibration;
 pub mod autostart;
 pub mod smartgesture;
+pub mod device;
+pub mod alias;
+pub mod watch;
 
 // Add more command modules here as needed
\ No newline at end of file
@@ -1,12 +1,13 @@
-use std::sync::Arc;
 use anyhow::Result;
-use tokio::sync::Mutex;
 
-use crate::iqos::IqosBle;
+use crate::i18n::{tr, translate};
+
+use crate::iqos::SharedIqos;
 use crate::iqos::IqosIluma;
+use crate::loader::output;
 use crate::loader::parser::IQOSConsole;
 
-use super::command::{CommandRegistry, CommandInfo};
+use super::command::{CommandError, CommandInfo, Capabilities};
 
 pub fn command_info() -> CommandInfo {
     CommandInfo::new(
@@ -16,39 +17,41 @@ pub fn command_info() -> CommandInfo {
         true,  // Requires ILUMA model
         false, // Does not require ILUMA-i model
     )
+    .with_completer(complete_args)
+}
+
+/// Complete `enable|disable`
+fn complete_args(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    match args.len() {
+        0 => vec!["enable", "disable"],
+        _ => vec![],
+    }
 }
 
 pub async fn register_command(console: &IQOSConsole) {
-    console.register_command("smartgesture", Box::new(|iqos, args| {
+    console.register_command(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
     })).await;
 }
 
-pub async fn execute_command(iqos: Arc<Mutex<IqosBle>>, args: Vec<String>) -> Result<()> {
+pub async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
     let iqos = iqos.lock().await;
-    if iqos.is_iluma_or_higher() {
-        match args.get(1).map(|s| s.as_str()) {
-            Some("enable") => {
-                let result = IqosIluma::update_smartgesture(&*iqos, true).await;
-                match result {
-                    Ok(_) => println!("Smart Gesture enabled"),
-                    Err(e) => println!("Error: {}", e),
-                }
-            },
-            Some("disable") => {
-                let result = IqosIluma::update_smartgesture(&*iqos, false).await;
-                match result {
-                    Ok(_) => println!("Smart Gesture disabled"),
-                    Err(e) => println!("Error: {}", e),
-                }
-            },
-            Some(opt) => println!("Invalid option: {}. Please specify 'enable' or 'disable'", opt),
-            None => println!("Usage: smartgesture [enable|disable]"),
-        }
-    } else {
-        println!("This device is not an ILUMA model");
+    let Some(iluma) = iqos.as_iluma() else {
+        return Err(CommandError::unsupported_model("ILUMA").into());
+    };
+    match args.get(1).map(|s| s.as_str()) {
+        Some("enable") => {
+            IqosIluma::update_smartgesture(iluma, true).await?;
+            output::message(tr!("Smart Gesture enabled"));
+        },
+        Some("disable") => {
+            IqosIluma::update_smartgesture(iluma, false).await?;
+            output::message(tr!("Smart Gesture disabled"));
+        },
+        Some(opt) => return Err(CommandError::Usage(tr!("Invalid option: {}. Please specify 'enable' or 'disable'", opt)).into()),
+        None => return Err(CommandError::Usage(translate(command_info().usage).to_string()).into()),
     }
     Ok(())
 }
@@ -1,13 +1,14 @@
-use std::sync::Arc;
 use anyhow::Result;
-use tokio::sync::Mutex;
 
-use crate::iqos::IqosBle;
+use crate::i18n::{tr, translate};
+
+use crate::iqos::SharedIqos;
 use crate::iqos::device::{Iqos, IqosIluma};
 use crate::iqos::vibration::{VibrationBehavior, VibrationSettings, IlumaVibrationBehavior};
+use crate::loader::output;
 use crate::loader::parser::IQOSConsole;
 
-use super::command::{CommandRegistry, CommandInfo};
+use super::command::{CommandError, CommandRegistry, CommandInfo, RegisteredCommand, Capabilities};
 
 /// Get information about the vibration command
 pub fn command_info() -> CommandInfo {
@@ -18,11 +19,25 @@ pub fn command_info() -> CommandInfo {
         false,  // Does not require ILUMA model
         false,  // Does not require ILUMA-i model
     )
+    .with_completer(complete_args)
+}
+
+/// Complete an option at even positions and `on|off` after it. The charge
+/// option is only offered on ILUMA models.
+fn complete_args(args: &[&str], capabilities: Capabilities) -> Vec<&'static str> {
+    if args.len() % 2 == 1 {
+        return vec!["on", "off"];
+    }
+    let charge = capabilities.iluma.then_some("charge");
+    charge.into_iter()
+        .chain(["heating", "starting", "terminated", "puffend"])
+        .filter(|option| !args.iter().step_by(2).any(|arg| arg == option))
+        .collect()
 }
 
 /// Register the vibration command
 pub async fn register_command(console: &IQOSConsole) {
-    console.register_command("vibration", Box::new(|iqos, args| {
+    console.register_command(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
@@ -31,48 +46,57 @@ pub async fn register_command(console: &IQOSConsole) {
 
 /// Register the vibration command using the registry directly
 pub fn register(commands: &mut CommandRegistry) {
-    commands.insert("vibration".to_string(), Box::new(|iqos, args| {
+    commands.insert("vibration".to_string(), RegisteredCommand::new(command_info(), Box::new(|iqos, args| {
         Box::pin(async move {
             execute_command(iqos, args).await
         })
-    }));
+    })));
 }
 
 /// Execute the vibration command
-async fn execute_command(iqos: Arc<Mutex<IqosBle>>, args: Vec<String>) -> Result<()> {
+async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
     let iqos = iqos.lock().await;
     let str_args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
     
     if str_args.len() >= 2 {
         let param_args = &str_args[1..];
+        validate_args(param_args, iqos.as_iluma().is_some())?;
         
-        if iqos.is_iluma_or_higher() {
+        if let Some(iluma) = iqos.as_iluma() {
             let settings = VibrationSettings::from_args_with_charge_start(param_args)?;
-            IqosIluma::update_iluma_vibration_settings(&*iqos, settings).await?;
-            println!("Vibration settings updated");
+            IqosIluma::update_iluma_vibration_settings(iluma, settings).await?;
         } else {
             let settings = VibrationSettings::from_args(param_args)?;
             Iqos::update_vibration_settings(&*iqos, settings).await?;
-            println!("Vibration settings updated");
-        }
-    } else if args.len() == 1 {
-        if iqos.is_iluma_or_higher() {
-            match IqosIluma::load_iluma_vibration_settings(&*iqos).await {
-                Ok(settings) => {
-                    println!("{}", settings);
-                },
-                Err(e) => println!("Error: {}", e),
-            }
-        } else {
-            match Iqos::load_vibration_settings(&*iqos).await {
-                Ok(settings) => println!("{}", settings),
-                Err(e) => println!("Error: {}", e),
-            }
         }
+        output::message(tr!("Vibration settings updated"));
+    } else if let Some(iluma) = iqos.as_iluma() {
+        let settings = IqosIluma::load_iluma_vibration_settings(iluma).await?;
+        output::print(&settings, &settings);
     } else {
-        println!("Usage: vibration [charge|heating|starting|terminated|puffend] [on|off] ...");
-        println!("Example: vibration charge on heating on puffend on");
-        println!("Note: charge option is only available for ILUMA models");
+        let settings = Iqos::load_vibration_settings(&*iqos).await?;
+        output::print(&settings, &settings);
+    }
+    Ok(())
+}
+
+/// Check that the arguments are `<option> <on|off>` pairs the model supports
+fn validate_args(args: &[&str], is_iluma: bool) -> std::result::Result<(), CommandError> {
+    let usage = || CommandError::Usage(translate(command_info().usage).to_string());
+
+    if args.len() % 2 != 0 {
+        return Err(usage());
+    }
+    for pair in args.chunks(2) {
+        match pair[0] {
+            "heating" | "starting" | "terminated" | "puffend" => {},
+            "charge" if is_iluma => {},
+            "charge" => return Err(CommandError::Unsupported(tr!("The charge option is only available for ILUMA models").to_string())),
+            _ => return Err(usage()),
+        }
+        if pair[1] != "on" && pair[1] != "off" {
+            return Err(usage());
+        }
     }
     Ok(())
 }
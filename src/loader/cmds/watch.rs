@@ -0,0 +1,131 @@
+use std::io::{stdout, IsTerminal, Write};
+
+use anyhow::Result;
+use console::style;
+use crossterm::cursor::MoveUp;
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType};
+use serde_json::{json, Value};
+
+use crate::i18n::{tr, translate};
+
+use crate::iqos::SharedIqos;
+use crate::iqos::device::{Iqos, IqosIluma};
+use crate::loader::output::{self, OutputFormat};
+use crate::loader::parser::IQOSConsole;
+use crate::loader::script::parse_duration;
+
+use super::command::{Capabilities, CommandError, CommandInfo};
+
+/// Commands that can be watched
+const WATCHABLE: [&str; 3] = ["battery", "vibration", "flexbattery"];
+
+/// Get information about the watch command
+pub fn command_info() -> CommandInfo {
+    CommandInfo::new(
+        "watch",
+        "Re-run a status command at an interval, highlighting changes",
+        "Usage: watch <interval> <battery|vibration|flexbattery>\nExample: watch 30s battery (Ctrl-C to stop)",
+        false, // Does not require ILUMA model
+        false, // Does not require ILUMA-i model
+    )
+    .with_completer(complete_args)
+}
+
+/// Complete a few intervals, then the commands the model supports
+fn complete_args(args: &[&str], capabilities: Capabilities) -> Vec<&'static str> {
+    match args.len() {
+        0 => vec!["5s", "30s", "1m"],
+        1 => WATCHABLE.into_iter()
+            .filter(|command| *command != "flexbattery" || capabilities.iluma_i)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Register the watch command
+pub async fn register_command(console: &IQOSConsole) {
+    console.register_command(command_info(), Box::new(|iqos, args| {
+        Box::pin(async move {
+            execute_command(iqos, args).await
+        })
+    })).await;
+}
+
+/// Execute the watch command
+async fn execute_command(iqos: SharedIqos, args: Vec<String>) -> Result<()> {
+    let usage = || CommandError::Usage(translate(command_info().usage).to_string());
+
+    let (Some(interval_arg), Some(target), None) = (args.get(1), args.get(2), args.get(3)) else {
+        return Err(usage().into());
+    };
+    let interval = parse_duration(interval_arg)
+        .filter(|interval| !interval.is_zero())
+        .ok_or_else(usage)?;
+    let target = target.to_lowercase();
+    if !WATCHABLE.contains(&target.as_str()) {
+        return Err(usage().into());
+    }
+
+    let redraw = output::format() == OutputFormat::Text && stdout().is_terminal();
+    let mut previous: Vec<String> = Vec::new();
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let time = output::clock_time();
+        let (value, text) = sample(&iqos, &target).await?;
+
+        match output::format() {
+            OutputFormat::Json => output::print(&json!({ "time": time, target.as_str(): value }), ""),
+            OutputFormat::Text => {
+                let lines: Vec<String> = text.lines().map(str::to_string).collect();
+                if redraw && !previous.is_empty() {
+                    // Move back over the header and the previous sample
+                    execute!(stdout(), MoveUp(previous.len() as u16 + 1), Clear(ClearType::FromCursorDown))?;
+                }
+                println!("{}", style(tr!("Every {}: {} at {} (Ctrl-C to stop)", interval_arg, target, time)).dim());
+                for (i, line) in lines.iter().enumerate() {
+                    if !previous.is_empty() && previous.get(i) != Some(line) {
+                        println!("{}", style(line).yellow().bold());
+                    } else {
+                        println!("{}", line);
+                    }
+                }
+                stdout().flush()?;
+                previous = lines;
+            }
+        }
+
+        tokio::select! {
+            _ = &mut ctrl_c => return Ok(()),
+            _ = tokio::time::sleep(interval) => {},
+        }
+    }
+}
+
+/// Load the watched value once, as JSON and as text
+async fn sample(iqos: &SharedIqos, target: &str) -> Result<(Value, String)> {
+    let mut iqos = iqos.lock().await;
+    match target {
+        "battery" => {
+            Iqos::reload_battery(&mut *iqos).await?;
+            let battery = Iqos::battery_status(&*iqos);
+            Ok((json!(battery), tr!("Battery status: {}%", battery)))
+        },
+        "vibration" => {
+            let settings = match iqos.as_iluma() {
+                Some(iluma) => IqosIluma::load_iluma_vibration_settings(iluma).await?,
+                None => Iqos::load_vibration_settings(&*iqos).await?,
+            };
+            Ok((serde_json::to_value(&settings)?, settings.to_string()))
+        },
+        _ => {
+            let Some(iluma_i) = iqos.as_iluma_i() else {
+                return Err(CommandError::unsupported_model("ILUMA i").into());
+            };
+            let flexbattery = iluma_i.load_flexbattery().await?;
+            Ok((serde_json::to_value(&flexbattery)?, flexbattery.to_string()))
+        },
+    }
+}
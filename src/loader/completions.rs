@@ -0,0 +1,115 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, Command, ValueEnum};
+use clap_complete::Shell;
+
+use crate::i18n::{tr, translate};
+use crate::loader::cmds::command::{Capabilities, CommandError, CommandInfo};
+use crate::loader::parser::known_command_infos;
+
+pub const COMPLETIONS_USAGE: &str = "Usage: completions <bash|zsh|fish>";
+
+/// Commands that only exist in one-shot mode
+const ONE_SHOT_COMMANDS: [(&str, &str); 5] = [
+    ("run", "Run the commands in a script file"),
+    ("tui", "Open the full-screen dashboard"),
+    ("daemon", "Keep devices connected and serve JSON-RPC on a Unix socket"),
+    ("serve", "Serve device settings as JSON over HTTP"),
+    ("completions", "Print a shell completion script"),
+];
+
+/// Ids of the positional arguments, which also bounds how deep the
+/// completers are explored
+const ARG_IDS: [&str; 12] = ["arg1", "arg2", "arg3", "arg4", "arg5", "arg6", "arg7", "arg8", "arg9", "arg10", "arg11", "arg12"];
+
+/// Print the completion script for `shell` to stdout
+pub fn print(cli: Command, shell: Option<&str>) -> Result<()> {
+    let shell = shell
+        .and_then(|name| Shell::from_str(name, true).ok())
+        .ok_or_else(|| CommandError::Usage(translate(COMPLETIONS_USAGE).to_string()))?;
+    generate(cli, shell, &mut std::io::stdout());
+    Ok(())
+}
+
+/// Write the completion script for the CLI, with every console command as a
+/// subcommand whose arguments come from the command's completer
+pub fn generate(cli: Command, shell: Shell, out: &mut dyn Write) {
+    let infos = known_command_infos();
+    let names: Vec<&'static str> = infos.iter().map(|info| info.name).collect();
+
+    let mut cli = cli.mut_arg("command", |arg| arg.hide(true)).disable_help_subcommand(true);
+    for info in &infos {
+        cli = cli.subcommand(command_for(info, &names));
+    }
+    for (name, about) in ONE_SHOT_COMMANDS {
+        let command = Command::new(name).about(translate(about));
+        let command = match name {
+            "run" => command.arg(Arg::new("file").value_hint(clap::ValueHint::FilePath)),
+            "completions" => command.arg(Arg::new("shell").value_parser(["bash", "zsh", "fish"])),
+            _ => command,
+        };
+        cli = cli.subcommand(command);
+    }
+
+    let bin_name = cli.get_name().to_string();
+    clap_complete::generate(shell, &mut cli, bin_name.clone(), out);
+
+    // The fish generator leaves out the values of positional arguments
+    if shell == Shell::Fish {
+        for info in &infos {
+            let mut values = if info.name == "help" { names.clone() } else { argument_values(info).concat() };
+            values.sort_unstable();
+            values.dedup();
+            if !values.is_empty() {
+                let _ = writeln!(out, "complete -c {} -n \"__fish_{}_using_subcommand {}\" -f -a \"{}\"",
+                    bin_name, bin_name, info.name, values.join(" "));
+            }
+        }
+    }
+}
+
+fn command_for(info: &CommandInfo, names: &[&'static str]) -> Command {
+    let mut command = Command::new(info.name)
+        .about(translate(info.description))
+        .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).help(tr!("Print the result as JSON")));
+
+    if info.name == "help" {
+        return command.arg(Arg::new("topic").value_parser(PossibleValuesParser::new(names.to_vec())));
+    }
+    for (id, values) in ARG_IDS.into_iter().zip(argument_values(info)) {
+        command = command.arg(Arg::new(id).value_parser(PossibleValuesParser::new(values)));
+    }
+    command
+}
+
+/// The values the completer offers at each position, over every way of
+/// filling the earlier positions. All model features are assumed, as the
+/// script is generated without a device.
+fn argument_values(info: &CommandInfo) -> Vec<Vec<&'static str>> {
+    let capabilities = Capabilities { iluma: true, iluma_i: true };
+    let mut prefixes: Vec<Vec<&'static str>> = vec![vec![]];
+    let mut positions = Vec::new();
+
+    while positions.len() < ARG_IDS.len() {
+        let mut values: Vec<&'static str> = Vec::new();
+        let mut next = Vec::new();
+        for prefix in &prefixes {
+            for value in info.complete(prefix, capabilities) {
+                if !values.contains(&value) {
+                    values.push(value);
+                }
+                let mut longer = prefix.clone();
+                longer.push(value);
+                next.push(longer);
+            }
+        }
+        if values.is_empty() {
+            break;
+        }
+        positions.push(values);
+        prefixes = next;
+    }
+    positions
+}
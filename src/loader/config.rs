@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::i18n::{tr, Lang};
+use crate::loader::daemon;
+use crate::loader::history::History;
+use crate::loader::output::OutputFormat;
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Commands kept in the history when `history_size` is not set
+const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// User settings from `config.toml` in the user's config directory.
+/// Command line flags take precedence over these.
+///
+/// ```toml
+/// adapter = "hci1"
+/// device = "kitchen"
+/// output = "json"
+/// language = "ja"
+/// history = "~/.local/state/iqos_cli/history.txt"
+/// history_size = 500
+/// history_per_device = true
+/// confirm = false
+/// socket = "/run/user/1000/iqos_cli/daemon.sock"
+///
+/// [timeouts]
+/// scan = 20
+/// connect = 15
+///
+/// [aliases]
+/// night = "brightness low; vibration heating off starting off"
+/// vib = "vibration $1 $2"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Bluetooth adapter, by name (hci1) or index
+    pub adapter: Option<String>,
+    /// Known device to connect to, by alias or serial number
+    pub device: Option<String>,
+    /// Output format for command results
+    pub output: Option<OutputFormat>,
+    /// Language for messages
+    pub language: Option<Lang>,
+    /// Console history file; `~/` is the home directory. Defaults to
+    /// `$XDG_STATE_HOME/iqos_cli/history.txt`
+    pub history: Option<PathBuf>,
+    /// Number of commands kept in the history
+    pub history_size: Option<usize>,
+    /// Keep a separate default history file for each device serial number
+    pub history_per_device: bool,
+    /// Unix socket of the daemon; `~/` is the home directory
+    pub socket: Option<PathBuf>,
+    /// Ask before commands that lock the device or forget it. On unless
+    /// turned off here or with `--yes`.
+    pub confirm: bool,
+    pub timeouts: Timeouts,
+    /// Command aliases and macros: name -> `;` separated commands, where
+    /// `$1`..`$9` and `$*` are replaced by the arguments
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            adapter: None,
+            device: None,
+            output: None,
+            language: None,
+            history: None,
+            history_size: None,
+            history_per_device: false,
+            socket: None,
+            confirm: true,
+            timeouts: Timeouts::default(),
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+/// Timeouts in seconds
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Timeouts {
+    /// Scanning for a known device
+    pub scan: u64,
+    /// Each step of connecting and initializing a device
+    pub connect: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self { scan: 10, connect: 10 }
+    }
+}
+
+impl Timeouts {
+    pub fn scan(&self) -> Duration {
+        Duration::from_secs(self.scan)
+    }
+
+    pub fn connect(&self) -> Duration {
+        Duration::from_secs(self.connect)
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("iqos_cli").join(CONFIG_FILE))
+    }
+
+    /// Load the config, using the defaults if there is no file
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| match toml::from_str(&content) {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    eprintln!("{}", tr!("Ignoring invalid {}: {}", CONFIG_FILE, e));
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        match &self.socket {
+            Some(path) => expand_home(path),
+            None => daemon::default_socket_path(),
+        }
+    }
+
+    /// The console history of the device with this serial number
+    pub fn history(&self, serial: &str) -> History {
+        let path = match &self.history {
+            Some(path) => expand_home(path),
+            None => History::default_path(self.history_per_device.then_some(serial)),
+        };
+        History::load(path, self.history_size.unwrap_or(DEFAULT_HISTORY_SIZE))
+    }
+}
+
+/// Replace a leading `~/` with the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
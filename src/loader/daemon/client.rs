@@ -0,0 +1,219 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex as StdMutex;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+use tokio::sync::Mutex;
+
+use crate::i18n::tr;
+use crate::iqos::device::{DeviceInfo, Iqos, IqosIluma, IqosIlumaI};
+use crate::iqos::error::{IQOSError, Result};
+use crate::iqos::flexbattery::FlexBattery;
+use crate::iqos::{BrightnessLevel, Flexpuff, IQOSModel, VibrationSettings};
+
+use super::rpc::{Request, Response};
+
+struct Connection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    /// Run `method` on the daemon. In a dry run, the frames it kept from the
+    /// device are added to `dry_run_frames`, also when the method fails.
+    async fn call<T: DeserializeOwned>(&mut self, id: u64, method: &str, params: Value, dry_run_frames: &mut Vec<String>) -> Result<T> {
+        let mut json = serde_json::to_string(&Request::new(id, method, params)).map_err(lost)?;
+        json.push('\n');
+        self.writer.write_all(json.as_bytes()).await.map_err(lost)?;
+
+        let line = self.lines.next_line().await.map_err(lost)?
+            .ok_or_else(|| lost("closed"))?;
+        let response: Response = serde_json::from_str(&line).map_err(lost)?;
+        dry_run_frames.extend(response.dry_run_frames);
+        if let Some(error) = response.error {
+            return Err(error.into());
+        }
+        serde_json::from_value(response.result.unwrap_or(Value::Null)).map_err(lost)
+    }
+}
+
+fn lost(error: impl std::fmt::Display) -> IQOSError {
+    IQOSError::AdapterError(tr!("Lost the connection to the daemon: {}", error))
+}
+
+/// A device connected through the daemon. Every operation is a JSON-RPC call,
+/// so the console and one-shot commands work as with a local device.
+pub struct RemoteIqos {
+    connection: Mutex<Connection>,
+    next_id: AtomicU64,
+    info: DeviceInfo,
+    charger_battery: Option<u8>,
+    dry_run: AtomicBool,
+    dry_run_frames: StdMutex<Vec<String>>,
+}
+
+impl RemoteIqos {
+    /// Start a session for `device` (alias or serial number), or the daemon's
+    /// default device
+    pub async fn connect(stream: UnixStream, device: Option<&str>) -> Result<Self> {
+        let (reader, writer) = stream.into_split();
+        let mut connection = Connection { lines: BufReader::new(reader).lines(), writer };
+        let info: DeviceInfo = connection.call(0, "device_info", json!({ "device": device }), &mut Vec::new()).await?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            next_id: AtomicU64::new(1),
+            info,
+            charger_battery: None,
+            dry_run: AtomicBool::new(false),
+            dry_run_frames: StdMutex::new(Vec::new()),
+        })
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, mut params: Value) -> Result<T> {
+        params["device"] = json!(self.info.serial_number);
+        params["dry_run"] = json!(self.is_dry_run());
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut frames = Vec::new();
+        let result = self.connection.lock().await.call(id, method, params, &mut frames).await;
+        if let Ok(mut dry_run_frames) = self.dry_run_frames.lock() {
+            dry_run_frames.extend(frames);
+        }
+        result
+    }
+}
+
+impl std::fmt::Display for RemoteIqos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.info)
+    }
+}
+
+#[async_trait]
+impl Iqos for RemoteIqos {
+    fn model(&self) -> &IQOSModel {
+        &self.info.model
+    }
+
+    fn serial_number(&self) -> &str {
+        &self.info.serial_number
+    }
+
+    fn device_info(&self) -> DeviceInfo {
+        self.info.clone()
+    }
+
+    /// Each call asks the daemon for a dry run, which returns the frames it
+    /// kept from the device with the result
+    fn set_dry_run(&self, enabled: bool) {
+        self.dry_run.store(enabled, Ordering::Relaxed);
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run.load(Ordering::Relaxed)
+    }
+
+    fn take_dry_run_frames(&self) -> Vec<String> {
+        self.dry_run_frames.lock().map(|mut frames| std::mem::take(&mut *frames)).unwrap_or_default()
+    }
+
+    fn as_iluma(&self) -> Option<&dyn IqosIluma> {
+        matches!(self.info.model, IQOSModel::Iluma | IQOSModel::IlumaI).then_some(self as &dyn IqosIluma)
+    }
+
+    fn as_iluma_i(&self) -> Option<&dyn IqosIlumaI> {
+        matches!(self.info.model, IQOSModel::IlumaI).then_some(self as &dyn IqosIlumaI)
+    }
+
+    /// Ends this session only; the daemon keeps the device connected
+    async fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn reload_battery(&mut self) -> Result<()> {
+        self.info.battery = self.call("reload_battery", json!({})).await?;
+        self.charger_battery = self.call("charger_battery_status", json!({})).await?;
+        Ok(())
+    }
+
+    fn battery_status(&self) -> u8 {
+        self.info.battery
+    }
+
+    fn charger_battery_status(&self) -> Option<u8> {
+        self.charger_battery
+    }
+
+    async fn vibrate(&self) -> Result<()> {
+        self.call("vibrate", json!({})).await
+    }
+
+    async fn stop_vibrate(&self) -> Result<()> {
+        self.call("stop_vibrate", json!({})).await
+    }
+
+    async fn lock_device(&self) -> Result<()> {
+        self.call("lock_device", json!({})).await
+    }
+
+    async fn unlock_device(&self) -> Result<()> {
+        self.call("unlock_device", json!({})).await
+    }
+
+    async fn load_brightness(&self) -> Result<BrightnessLevel> {
+        self.call("load_brightness", json!({})).await
+    }
+
+    async fn update_brightness(&self, level: BrightnessLevel) -> Result<()> {
+        self.call("update_brightness", json!({ "level": level })).await
+    }
+
+    async fn load_vibration_settings(&self) -> Result<VibrationSettings> {
+        self.call("load_vibration_settings", json!({})).await
+    }
+
+    async fn update_vibration_settings(&self, settings: VibrationSettings) -> Result<()> {
+        self.call("update_vibration_settings", json!({ "settings": settings })).await
+    }
+}
+
+#[async_trait]
+impl IqosIluma for RemoteIqos {
+    async fn load_iluma_vibration_settings(&self) -> Result<VibrationSettings> {
+        self.call("load_iluma_vibration_settings", json!({})).await
+    }
+
+    async fn update_iluma_vibration_settings(&self, settings: VibrationSettings) -> Result<()> {
+        self.call("update_iluma_vibration_settings", json!({ "settings": settings })).await
+    }
+
+    async fn update_smartgesture(&self, enable: bool) -> Result<()> {
+        self.call("update_smartgesture", json!({ "enable": enable })).await
+    }
+
+    async fn update_autostart(&self, enable: bool) -> Result<()> {
+        self.call("update_autostart", json!({ "enable": enable })).await
+    }
+
+    async fn load_flexpuff(&self) -> Result<Flexpuff> {
+        self.call("load_flexpuff", json!({})).await
+    }
+
+    async fn update_flexpuff(&self, setting: Flexpuff) -> Result<()> {
+        self.call("update_flexpuff", json!({ "setting": setting })).await
+    }
+}
+
+#[async_trait]
+impl IqosIlumaI for RemoteIqos {
+    async fn update_flexbattery(&self, new: FlexBattery) -> Result<()> {
+        self.call("update_flexbattery", json!({ "flexbattery": new })).await
+    }
+
+    async fn load_flexbattery(&self) -> Result<FlexBattery> {
+        self.call("load_flexbattery", json!({})).await
+    }
+}
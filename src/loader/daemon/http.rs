@@ -0,0 +1,214 @@
+//! `iqos_cli serve --http ADDR`: the device settings as JSON resources over
+//! HTTP, for dashboards and home automation.
+//!
+//! | Method  | Path                             | Body                         |
+//! |---------|----------------------------------|------------------------------|
+//! | GET     | `/devices`                       |                              |
+//! | GET     | `/battery`                       |                              |
+//! | GET     | `/devices/{id}/battery`          |                              |
+//! | GET/PUT | `/devices/{id}/brightness`       | `{"brightness":"high"}`      |
+//! | GET/PUT | `/devices/{id}/vibration`        | vibration settings           |
+//! | GET/PUT | `/devices/{id}/flexpuff`         | flexpuff setting             |
+//! | GET/PUT | `/devices/{id}/flexbattery`      | flexbattery setting          |
+//! | PUT     | `/devices/{id}/autostart`        | `{"autostart":true}`         |
+//! | PUT     | `/devices/{id}/smartgesture`     | `{"smartgesture":true}`      |
+//! | PUT     | `/devices/{id}/lock`             | `{"locked":true}`            |
+//!
+//! `{id}` is an alias or serial number. Errors are `{"error":"..."}` with a
+//! matching status. There is no authentication, so keep the address local.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::i18n::{tr, translate};
+use crate::iqos::device::{IqosIluma, IqosIlumaI};
+use crate::iqos::error::IQOSError;
+use crate::iqos::{Iqos, IqosScanner};
+use crate::loader::cmds::command::CommandError;
+use crate::loader::config::Config;
+
+use super::rpc::{RpcError, CONNECTION_ERROR, INVALID_PARAMS, METHOD_NOT_FOUND, TIMEOUT, UNSUPPORTED};
+use super::server::Daemon;
+
+pub const SERVE_USAGE: &str = "Usage: serve --http <ADDR> [device...]";
+
+/// Connect to the devices named after the address (or the configured or
+/// preferred one), then answer HTTP requests until Ctrl-C. Devices named in
+/// requests are connected on first use.
+pub async fn serve(scanner: IqosScanner, config: &Config, args: &[String]) -> Result<()> {
+    let (addr, names) = parse_args(args)?;
+    let listener = TcpListener::bind(addr).await
+        .with_context(|| tr!("Could not listen on {}", addr))?;
+    let daemon = Arc::new(Daemon::new(scanner, config));
+    daemon.connect_startup(names).await;
+    println!("{}", tr!("Listening on http://{} (Ctrl-C to stop)", addr));
+
+    let app = Router::new()
+        .route("/devices", get(devices))
+        .route("/battery", get(default_battery))
+        .route("/devices/{id}/{resource}", get(load).put(update))
+        .with_state(daemon.clone());
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async { let _ = tokio::signal::ctrl_c().await; })
+        .await?;
+
+    daemon.disconnect_all().await;
+    Ok(())
+}
+
+/// `--http <ADDR>` and the device names around it
+fn parse_args(args: &[String]) -> Result<(SocketAddr, &[String]), CommandError> {
+    let usage = || CommandError::Usage(translate(SERVE_USAGE).to_string());
+    match args {
+        [flag, addr, names @ ..] if flag == "--http" => Ok((addr.parse().map_err(|_| usage())?, names)),
+        _ => Err(usage()),
+    }
+}
+
+/// An error as `{"error": message}`, with the status matching its cause
+struct HttpError(StatusCode, String);
+
+impl From<RpcError> for HttpError {
+    fn from(error: RpcError) -> Self {
+        let status = match error.code {
+            INVALID_PARAMS => StatusCode::BAD_REQUEST,
+            METHOD_NOT_FOUND => StatusCode::NOT_FOUND,
+            UNSUPPORTED => StatusCode::NOT_IMPLEMENTED,
+            CONNECTION_ERROR => StatusCode::SERVICE_UNAVAILABLE,
+            TIMEOUT => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        HttpError(status, error.message)
+    }
+}
+
+impl From<IQOSError> for HttpError {
+    fn from(error: IQOSError) -> Self {
+        RpcError::from(error).into()
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type HttpResult = std::result::Result<Json<Value>, HttpError>;
+
+async fn devices(State(daemon): State<Arc<Daemon>>) -> HttpResult {
+    let mut infos = Vec::new();
+    for iqos in daemon.connected().await {
+        infos.push(iqos.lock().await.device_info());
+    }
+    Ok(Json(json!(infos)))
+}
+
+async fn default_battery(State(daemon): State<Arc<Daemon>>) -> HttpResult {
+    on_device(&daemon, None, "battery", None).await
+}
+
+async fn load(State(daemon): State<Arc<Daemon>>, Path((id, resource)): Path<(String, String)>) -> HttpResult {
+    on_device(&daemon, Some(&id), &resource, None).await
+}
+
+async fn update(State(daemon): State<Arc<Daemon>>, Path((id, resource)): Path<(String, String)>, body: Bytes) -> HttpResult {
+    let body: Value = serde_json::from_slice(&body)
+        .map_err(|e| HttpError(StatusCode::BAD_REQUEST, tr!("Invalid JSON body: {}", e)))?;
+    on_device(&daemon, Some(&id), &resource, Some(body)).await
+}
+
+/// Read the resource, or update it from `body`, on the device called `name`
+async fn on_device(daemon: &Daemon, name: Option<&str>, resource: &str, body: Option<Value>) -> HttpResult {
+    let shared = daemon.device(name).await?;
+    let mut iqos = shared.lock().await;
+    let result = match body {
+        None => load_resource(&mut *iqos, resource).await,
+        Some(body) => update_resource(&*iqos, resource, body).await,
+    };
+
+    // Reconnect on the next request rather than keep a dead connection
+    if matches!(&result, Err(HttpError(StatusCode::SERVICE_UNAVAILABLE, _))) {
+        let serial = iqos.serial_number().to_string();
+        drop(iqos);
+        daemon.forget(&serial).await;
+    }
+    result.map(Json)
+}
+
+async fn load_resource(iqos: &mut dyn Iqos, resource: &str) -> std::result::Result<Value, HttpError> {
+    Ok(match resource {
+        "battery" => {
+            iqos.reload_battery().await?;
+            json!({ "battery": iqos.battery_status(), "charger": iqos.charger_battery_status() })
+        },
+        "brightness" => json!({ "brightness": iqos.load_brightness().await? }),
+        "vibration" => match iqos.as_iluma() {
+            Some(iluma) => json!(iluma.load_iluma_vibration_settings().await?),
+            None => json!(iqos.load_vibration_settings().await?),
+        },
+        "flexpuff" => json!(iluma(iqos)?.load_flexpuff().await?),
+        "flexbattery" => json!(iluma_i(iqos)?.load_flexbattery().await?),
+        "autostart" | "smartgesture" | "lock" => {
+            return Err(HttpError(StatusCode::METHOD_NOT_ALLOWED, tr!("{} cannot be read from the device, only set with PUT", resource)));
+        },
+        _ => return Err(unknown(resource)),
+    })
+}
+
+/// Apply `body` and answer with it, as the device does not report the result
+async fn update_resource(iqos: &dyn Iqos, resource: &str, body: Value) -> std::result::Result<Value, HttpError> {
+    match resource {
+        "brightness" => iqos.update_brightness(field(&body, "brightness")?).await?,
+        "vibration" => match iqos.as_iluma() {
+            Some(iluma) => iluma.update_iluma_vibration_settings(parse(&body)?).await?,
+            None => iqos.update_vibration_settings(parse(&body)?).await?,
+        },
+        "flexpuff" => iluma(iqos)?.update_flexpuff(parse(&body)?).await?,
+        "flexbattery" => iluma_i(iqos)?.update_flexbattery(parse(&body)?).await?,
+        "autostart" => iluma(iqos)?.update_autostart(field(&body, "autostart")?).await?,
+        "smartgesture" => iluma(iqos)?.update_smartgesture(field(&body, "smartgesture")?).await?,
+        "lock" => match field(&body, "locked")? {
+            true => iqos.lock_device().await?,
+            false => iqos.unlock_device().await?,
+        },
+        "battery" => return Err(HttpError(StatusCode::METHOD_NOT_ALLOWED, tr!("{} can only be read", resource))),
+        _ => return Err(unknown(resource)),
+    }
+    Ok(body)
+}
+
+fn unknown(resource: &str) -> HttpError {
+    HttpError(StatusCode::NOT_FOUND, tr!("Unknown resource: {}", resource))
+}
+
+fn iluma(iqos: &dyn Iqos) -> std::result::Result<&dyn IqosIluma, HttpError> {
+    iqos.as_iluma().ok_or_else(|| IQOSError::IncompatibleModelError.into())
+}
+
+fn iluma_i(iqos: &dyn Iqos) -> std::result::Result<&dyn IqosIlumaI, HttpError> {
+    iqos.as_iluma_i().ok_or_else(|| IQOSError::IncompatibleModelError.into())
+}
+
+fn parse<T: DeserializeOwned>(body: &Value) -> std::result::Result<T, HttpError> {
+    serde_json::from_value(body.clone())
+        .map_err(|e| HttpError(StatusCode::BAD_REQUEST, tr!("Invalid JSON body: {}", e)))
+}
+
+fn field<T: DeserializeOwned>(body: &Value, name: &str) -> std::result::Result<T, HttpError> {
+    let value = body.get(name)
+        .ok_or_else(|| HttpError(StatusCode::BAD_REQUEST, tr!("Missing field: {}", name)))?;
+    serde_json::from_value(value.clone())
+        .map_err(|e| HttpError(StatusCode::BAD_REQUEST, tr!("Invalid field {}: {}", name, e)))
+}
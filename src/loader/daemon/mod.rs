@@ -0,0 +1,37 @@
+//! `iqos_cli daemon`: keeps devices connected and serves JSON-RPC 2.0 on a
+//! Unix socket, one JSON object per line.
+//!
+//! Methods are named after the `Iqos`, `IqosIluma` and `IqosIlumaI` trait
+//! methods (`load_brightness`, `update_flexpuff`, ...), plus `devices` and
+//! `disconnect`. `params` is an object holding the method's arguments, the
+//! target `device` (alias or serial number) and an optional `dry_run` flag:
+//!
+//! ```json
+//! {"jsonrpc":"2.0","id":1,"method":"update_brightness","params":{"device":"kitchen","level":"low"}}
+//! ```
+//!
+//! In a dry run, the response also holds `dry_run_frames`: descriptions of
+//! the frames that were not sent, for the client to show.
+//!
+//! `iqos_cli serve --http ADDR` serves the same devices as JSON resources over
+//! HTTP instead; see [`http`].
+
+pub mod client;
+pub mod http;
+pub mod rpc;
+pub mod server;
+
+use std::path::PathBuf;
+
+pub use client::RemoteIqos;
+pub use server::serve;
+
+/// `$XDG_RUNTIME_DIR/iqos_cli/daemon.sock`, or the state directory where
+/// there is no runtime directory
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::state_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("iqos_cli")
+        .join("daemon.sock")
+}
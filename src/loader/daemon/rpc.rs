@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::iqos::error::IQOSError;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The device rejected or could not parse a request
+pub const DEVICE_ERROR: i64 = -32000;
+/// The device model does not support the method
+pub const UNSUPPORTED: i64 = -32001;
+/// Bluetooth failed, or the device could not be found or connected
+pub const CONNECTION_ERROR: i64 = -32002;
+/// The device did not answer in time
+pub const TIMEOUT: i64 = -32003;
+
+/// A JSON-RPC 2.0 request. `params` is an object; `device` in it selects the
+/// device by alias or serial number.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: id.into(),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    /// In a dry run, the frames that were not sent to the device
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dry_run_frames: Vec<String>,
+}
+
+impl Response {
+    pub fn new(id: Value, result: std::result::Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self { jsonrpc: "2.0".to_string(), id, result, error, dry_run_frames: Vec::new() }
+    }
+
+    pub fn with_dry_run_frames(mut self, frames: Vec<String>) -> Self {
+        self.dry_run_frames = frames;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl From<IQOSError> for RpcError {
+    fn from(error: IQOSError) -> Self {
+        match error {
+            IQOSError::ConfigurationError(msg) | IQOSError::AutoStartError(msg) => RpcError::new(DEVICE_ERROR, msg),
+            IQOSError::NotIluma(_) | IQOSError::IncompatibleModelError => RpcError::new(UNSUPPORTED, error.to_string()),
+            IQOSError::AdapterError(msg) => RpcError::new(CONNECTION_ERROR, msg),
+            IQOSError::BleError(_) => RpcError::new(CONNECTION_ERROR, error.to_string()),
+            IQOSError::TimeoutError(msg) => RpcError::new(TIMEOUT, msg),
+        }
+    }
+}
+
+impl From<RpcError> for IQOSError {
+    fn from(error: RpcError) -> Self {
+        match error.code {
+            UNSUPPORTED => IQOSError::IncompatibleModelError,
+            CONNECTION_ERROR => IQOSError::AdapterError(error.message),
+            TIMEOUT => IQOSError::TimeoutError(error.message),
+            _ => IQOSError::ConfigurationError(error.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn encodes_requests() {
+        let request = Request::new(7, "update_brightness", json!({ "device": "kitchen", "level": "low" }));
+        assert_eq!(serde_json::to_value(&request).unwrap(), json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "update_brightness",
+            "params": { "device": "kitchen", "level": "low" },
+        }));
+    }
+
+    #[test]
+    fn decodes_requests_without_id_or_params() {
+        let request: Request = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"devices"}"#).unwrap();
+        assert!(request.id.is_null());
+        assert!(request.params.is_null());
+    }
+
+    #[test]
+    fn encodes_results_and_errors() {
+        let ok = Response::new(json!(1), Ok(json!("high")));
+        assert_eq!(serde_json::to_value(&ok).unwrap(), json!({ "jsonrpc": "2.0", "id": 1, "result": "high" }));
+
+        let failed = Response::new(json!(2), Err(RpcError::new(TIMEOUT, "slow")))
+            .with_dry_run_frames(vec!["frame".to_string()]);
+        assert_eq!(serde_json::to_value(&failed).unwrap(), json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "error": { "code": TIMEOUT, "message": "slow" },
+            "dry_run_frames": ["frame"],
+        }));
+    }
+
+    #[test]
+    fn maps_device_errors_to_codes() {
+        let code = |error: IQOSError| RpcError::from(error).code;
+        assert_eq!(code(IQOSError::ConfigurationError("bad".into())), DEVICE_ERROR);
+        assert_eq!(code(IQOSError::AutoStartError("bad".into())), DEVICE_ERROR);
+        assert_eq!(code(IQOSError::IncompatibleModelError), UNSUPPORTED);
+        assert_eq!(code(IQOSError::AdapterError("gone".into())), CONNECTION_ERROR);
+        assert_eq!(code(IQOSError::TimeoutError("slow".into())), TIMEOUT);
+    }
+
+    #[test]
+    fn maps_codes_back_to_device_errors() {
+        let error = |code: i64| IQOSError::from(RpcError::new(code, "message"));
+        assert!(matches!(error(UNSUPPORTED), IQOSError::IncompatibleModelError));
+        assert!(matches!(error(CONNECTION_ERROR), IQOSError::AdapterError(m) if m == "message"));
+        assert!(matches!(error(TIMEOUT), IQOSError::TimeoutError(m) if m == "message"));
+        assert!(matches!(error(DEVICE_ERROR), IQOSError::ConfigurationError(m) if m == "message"));
+        assert!(matches!(error(INVALID_PARAMS), IQOSError::ConfigurationError(_)));
+    }
+}
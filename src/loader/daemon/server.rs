@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{debug, instrument, warn};
+
+use crate::i18n::tr;
+use crate::iqos::device::{IqosIluma, IqosIlumaI};
+use crate::iqos::error::IQOSError;
+use crate::iqos::{Iqos, IqosScanner, SharedIqos};
+use crate::loader::config::Config;
+use crate::loader::known_devices::{connect_known, KnownDevices};
+
+use super::rpc::{
+    Request, Response, RpcError, CONNECTION_ERROR, DEVICE_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+};
+
+/// A device that is connected, or still connecting while the cell is empty
+type Slot = Arc<OnceCell<SharedIqos>>;
+
+/// The connected devices, by serial number, and how to connect more
+pub(super) struct Daemon {
+    /// `None` in tests, which insert their devices up front
+    scanner: Option<IqosScanner>,
+    scan_timeout: Duration,
+    connect_timeout: Duration,
+    /// Device used when a request names none and several are connected
+    default_device: Option<String>,
+    devices: Mutex<HashMap<String, Slot>>,
+}
+
+/// Connect to the devices named in `names` (or the configured or preferred
+/// one), then answer JSON-RPC requests on `socket` until Ctrl-C. Devices
+/// named in requests are connected on first use.
+pub async fn serve(scanner: IqosScanner, config: &Config, socket: &Path, names: &[String]) -> Result<()> {
+    let listener = bind(socket).await?;
+    let daemon = Arc::new(Daemon::new(scanner, config));
+    daemon.connect_startup(names).await;
+    println!("{}", tr!("Listening on {} (Ctrl-C to stop)", socket.display()));
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let daemon = daemon.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = daemon.handle(stream).await {
+                            debug!(error = %e, "client connection closed");
+                        }
+                    });
+                },
+                Err(e) => warn!(error = %e, "accept failed"),
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    let _ = fs::remove_file(socket);
+    daemon.disconnect_all().await;
+    Ok(())
+}
+
+/// Listen on `socket`, replacing a stale socket file. Only the user may connect.
+async fn bind(socket: &Path) -> Result<UnixListener> {
+    if UnixStream::connect(socket).await.is_ok() {
+        return Err(anyhow!(tr!("A daemon is already listening on {}", socket.display())));
+    }
+    if let Some(dir) = socket.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let _ = fs::remove_file(socket);
+    let listener = UnixListener::bind(socket)
+        .with_context(|| tr!("Could not listen on {}", socket.display()))?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+impl Daemon {
+    pub(super) fn new(scanner: IqosScanner, config: &Config) -> Self {
+        Self {
+            scanner: Some(scanner),
+            scan_timeout: config.timeouts.scan(),
+            connect_timeout: config.timeouts.connect(),
+            default_device: config.device.clone()
+                .or_else(|| KnownDevices::load().preferred().map(|device| device.serial.clone())),
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Connect to the devices named in `names`, or the default one
+    pub(super) async fn connect_startup(&self, names: &[String]) {
+        let startup: Vec<String> = match (names.is_empty(), &self.default_device) {
+            (false, _) => names.to_vec(),
+            (true, Some(device)) => vec![device.clone()],
+            (true, None) => vec![],
+        };
+        for name in &startup {
+            match self.device(Some(name)).await {
+                Ok(iqos) => println!("{}", tr!("Connected to {}", iqos.lock().await.serial_number())),
+                Err(e) => println!("{}", tr!("Could not connect to {}: {}", name, e.message)),
+            }
+        }
+    }
+
+    pub(super) async fn disconnect_all(&self) {
+        for iqos in self.connected().await {
+            let _ = iqos.lock().await.disconnect().await;
+        }
+    }
+
+    /// Answer newline delimited requests until the client hangs up
+    async fn handle(&self, stream: UnixStream) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(request) if request.jsonrpc != "2.0" => {
+                    Response::new(request.id, Err(RpcError::new(INVALID_REQUEST, tr!("Only JSON-RPC 2.0 is supported"))))
+                },
+                Ok(request) => {
+                    let mut dry_run_frames = Vec::new();
+                    let result = self.call(&request.method, &request.params, &mut dry_run_frames).await;
+                    // Notifications get no response
+                    if request.id.is_null() {
+                        continue;
+                    }
+                    Response::new(request.id, result).with_dry_run_frames(dry_run_frames)
+                },
+                Err(e) => Response::new(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+            };
+            let mut json = serde_json::to_string(&response)?;
+            json.push('\n');
+            writer.write_all(json.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    /// Run `method`. In a dry run, the frames kept from the device are added
+    /// to `dry_run_frames`, also when the method fails.
+    #[instrument(level = "debug", skip(self, params, dry_run_frames))]
+    async fn call(&self, method: &str, params: &Value, dry_run_frames: &mut Vec<String>) -> Result<Value, RpcError> {
+        let name = params.get("device").and_then(Value::as_str);
+        match method {
+            "devices" => {
+                let mut infos = Vec::new();
+                for iqos in self.connected().await {
+                    infos.push(iqos.lock().await.device_info());
+                }
+                return to_result(infos);
+            },
+            "disconnect" => {
+                let shared = self.device(name).await?;
+                let mut iqos = shared.lock().await;
+                let serial = iqos.serial_number().to_string();
+                iqos.disconnect().await?;
+                drop(iqos);
+                self.forget(&serial).await;
+                return Ok(Value::Null);
+            },
+            _ => {},
+        }
+
+        let shared = self.device(name).await?;
+        let mut iqos = shared.lock().await;
+        let dry_run = params.get("dry_run").and_then(Value::as_bool).unwrap_or(false);
+        let previous = iqos.is_dry_run();
+        iqos.set_dry_run(dry_run);
+        let result = call_device(&mut *iqos, method, params).await;
+        iqos.set_dry_run(previous);
+        dry_run_frames.extend(iqos.take_dry_run_frames());
+
+        // Reconnect on the next request rather than keep a dead connection
+        if matches!(&result, Err(e) if e.code == CONNECTION_ERROR) {
+            let serial = iqos.serial_number().to_string();
+            drop(iqos);
+            self.forget(&serial).await;
+        }
+        result
+    }
+
+    /// Drop the device from the connected ones, so it is connected again when
+    /// next used. The device must not be locked by the caller.
+    pub(super) async fn forget(&self, serial: &str) {
+        self.devices.lock().await.remove(serial);
+    }
+
+    /// The connected devices. The map is never locked while a device is, so
+    /// callers lock devices only after this returns.
+    pub(super) async fn connected(&self) -> Vec<SharedIqos> {
+        self.devices.lock().await.values().filter_map(|slot| slot.get().cloned()).collect()
+    }
+
+    /// The connected device called `name`, connecting it if it is known but
+    /// not connected yet. Without a name, the only connected device or the
+    /// default one is used.
+    pub(super) async fn device(&self, name: Option<&str>) -> Result<SharedIqos, RpcError> {
+        let name = match name {
+            Some(name) => name,
+            None => match self.connected().await.as_slice() {
+                [only] => return Ok(only.clone()),
+                _ => self.default_device.as_deref()
+                    .ok_or_else(|| RpcError::new(INVALID_PARAMS, tr!("No device specified. Pass \"device\" with an alias or serial number")))?,
+            },
+        };
+
+        let known = KnownDevices::load();
+        let target = known.find(name);
+        let serial = target.map_or(name, |device| device.serial.as_str());
+        let unknown = || RpcError::new(
+            CONNECTION_ERROR,
+            tr!("Unknown device '{}'. Use 'device list' in the console to see known devices", name),
+        );
+
+        // The map is only locked to find or add the slot. Requests for a
+        // device that is connecting wait on its slot, so it is connected once.
+        let slot = {
+            let mut devices = self.devices.lock().await;
+            match devices.get(serial) {
+                Some(slot) => slot.clone(),
+                None if target.is_some() => devices.entry(serial.to_string()).or_default().clone(),
+                None => return Err(unknown()),
+            }
+        };
+
+        let result = slot.get_or_try_init(|| async {
+            let (target, scanner) = target.zip(self.scanner.as_ref()).ok_or_else(unknown)?;
+            let (iqos, _) = connect_known(scanner, target, self.scan_timeout, self.connect_timeout).await
+                .map_err(|e| RpcError::new(CONNECTION_ERROR, e.to_string()))?;
+            Ok::<SharedIqos, RpcError>(Arc::new(Mutex::new(iqos)))
+        }).await.cloned();
+
+        if result.is_err() {
+            // Leave no empty slot behind, unless another request replaced it
+            let mut devices = self.devices.lock().await;
+            if devices.get(serial).is_some_and(|current| Arc::ptr_eq(current, &slot) && current.get().is_none()) {
+                devices.remove(serial);
+            }
+        }
+        result
+    }
+}
+
+/// Run a trait method on the device. Methods and parameters are named after
+/// the `Iqos`, `IqosIluma` and `IqosIlumaI` methods and their arguments.
+async fn call_device(iqos: &mut dyn Iqos, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "device_info" => to_result(iqos.device_info()),
+        "battery_status" => Ok(json!(iqos.battery_status())),
+        "charger_battery_status" => Ok(json!(iqos.charger_battery_status())),
+        "reload_battery" => {
+            iqos.reload_battery().await?;
+            Ok(json!(iqos.battery_status()))
+        },
+        "vibrate" => done(iqos.vibrate().await),
+        "stop_vibrate" => done(iqos.stop_vibrate().await),
+        "lock_device" => done(iqos.lock_device().await),
+        "unlock_device" => done(iqos.unlock_device().await),
+        "load_brightness" => to_result(iqos.load_brightness().await?),
+        "update_brightness" => done(iqos.update_brightness(param(params, "level")?).await),
+        "load_vibration_settings" => to_result(iqos.load_vibration_settings().await?),
+        "update_vibration_settings" => done(iqos.update_vibration_settings(param(params, "settings")?).await),
+        "load_iluma_vibration_settings" => to_result(iluma(iqos)?.load_iluma_vibration_settings().await?),
+        "update_iluma_vibration_settings" => done(iluma(iqos)?.update_iluma_vibration_settings(param(params, "settings")?).await),
+        "update_smartgesture" => done(iluma(iqos)?.update_smartgesture(param(params, "enable")?).await),
+        "update_autostart" => done(iluma(iqos)?.update_autostart(param(params, "enable")?).await),
+        "load_flexpuff" => to_result(iluma(iqos)?.load_flexpuff().await?),
+        "update_flexpuff" => done(iluma(iqos)?.update_flexpuff(param(params, "setting")?).await),
+        "load_flexbattery" => to_result(iluma_i(iqos)?.load_flexbattery().await?),
+        "update_flexbattery" => done(iluma_i(iqos)?.update_flexbattery(param(params, "flexbattery")?).await),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, tr!("Unknown method: {}", method))),
+    }
+}
+
+fn iluma(iqos: &dyn Iqos) -> Result<&dyn IqosIluma, RpcError> {
+    iqos.as_iluma().ok_or_else(|| IQOSError::IncompatibleModelError.into())
+}
+
+fn iluma_i(iqos: &dyn Iqos) -> Result<&dyn IqosIlumaI, RpcError> {
+    iqos.as_iluma_i().ok_or_else(|| IQOSError::IncompatibleModelError.into())
+}
+
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    let value = params.get(name)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, tr!("Missing parameter: {}", name)))?;
+    serde_json::from_value(value.clone())
+        .map_err(|e| RpcError::new(INVALID_PARAMS, tr!("Invalid parameter {}: {}", name, e)))
+}
+
+fn to_result<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(DEVICE_ERROR, e.to_string()))
+}
+
+/// `null` for methods that only report success
+fn done(result: crate::iqos::error::Result<()>) -> Result<Value, RpcError> {
+    result.map(|_| Value::Null).map_err(RpcError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iqos::mock::MockIqos;
+    use crate::iqos::{BrightnessLevel, IQOSModel};
+    use crate::loader::daemon::rpc::{TIMEOUT, UNSUPPORTED};
+    use crate::loader::daemon::RemoteIqos;
+
+    const SERIAL: &str = "MOCK0001";
+
+    impl Daemon {
+        fn with_device(serial: &str, iqos: SharedIqos) -> Self {
+            let devices = HashMap::from([(serial.to_string(), Arc::new(OnceCell::from(iqos)))]);
+            Self {
+                scanner: None,
+                scan_timeout: Duration::ZERO,
+                connect_timeout: Duration::ZERO,
+                default_device: None,
+                devices: Mutex::new(devices),
+            }
+        }
+    }
+
+    /// A daemon serving a mock device on one end of a socket pair, and the
+    /// other end
+    fn serve_mock(model: IQOSModel) -> (Arc<Mutex<MockIqos>>, UnixStream) {
+        let mock = Arc::new(Mutex::new(MockIqos::new(model, SERIAL, 64)));
+        let daemon = Daemon::with_device(SERIAL, mock.clone());
+        let (client, server) = UnixStream::pair().unwrap();
+        tokio::spawn(async move { daemon.handle(server).await });
+        (mock, client)
+    }
+
+    /// Send raw lines and read one response per line
+    async fn exchange(stream: UnixStream, lines: &[&str]) -> Vec<Response> {
+        let (reader, mut writer) = stream.into_split();
+        let mut replies = BufReader::new(reader).lines();
+        let mut responses = Vec::new();
+        for line in lines {
+            writer.write_all(format!("{}\n", line).as_bytes()).await.unwrap();
+            let reply = replies.next_line().await.unwrap().expect("response");
+            responses.push(serde_json::from_str(&reply).unwrap());
+        }
+        responses
+    }
+
+    fn error_code(response: &Response) -> Option<i64> {
+        response.error.as_ref().map(|error| error.code)
+    }
+
+    #[tokio::test]
+    async fn remote_calls_reach_the_device() {
+        let (mock, client) = serve_mock(IQOSModel::Iluma);
+        let mut remote = RemoteIqos::connect(client, Some(SERIAL)).await.unwrap();
+        assert_eq!(remote.serial_number(), SERIAL);
+        assert_eq!(remote.model(), &IQOSModel::Iluma);
+
+        remote.reload_battery().await.unwrap();
+        assert_eq!(remote.battery_status(), 64);
+        assert!(matches!(remote.load_brightness().await.unwrap(), BrightnessLevel::High));
+        remote.update_brightness(BrightnessLevel::Low).await.unwrap();
+        assert!(matches!(*mock.lock().await.brightness.lock().unwrap(), BrightnessLevel::Low));
+        remote.as_iluma().unwrap().update_autostart(true).await.unwrap();
+        assert!(*mock.lock().await.autostart.lock().unwrap());
+        remote.as_iluma().unwrap().update_smartgesture(true).await.unwrap();
+        assert!(*mock.lock().await.smartgesture.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn device_errors_keep_their_kind() {
+        let (mock, client) = serve_mock(IQOSModel::Iluma);
+        mock.lock().await.fail_with("update_smartgesture", IQOSError::TimeoutError("smartgesture".to_string()));
+        let remote = RemoteIqos::connect(client, Some(SERIAL)).await.unwrap();
+        let error = remote.as_iluma().unwrap().update_smartgesture(true).await.unwrap_err();
+        assert!(matches!(error, IQOSError::TimeoutError(_)));
+    }
+
+    #[tokio::test]
+    async fn unknown_devices_are_connection_errors() {
+        let (_, client) = serve_mock(IQOSModel::Iluma);
+        let error = RemoteIqos::connect(client, Some("NOT-CONNECTED")).await.err().expect("error");
+        assert!(matches!(error, IQOSError::AdapterError(_)));
+    }
+
+    #[tokio::test]
+    async fn dry_run_frames_come_back_with_the_result() {
+        let (mock, client) = serve_mock(IQOSModel::Iluma);
+        let remote = RemoteIqos::connect(client, Some(SERIAL)).await.unwrap();
+        remote.set_dry_run(true);
+        remote.update_brightness(BrightnessLevel::Low).await.unwrap();
+
+        assert_eq!(remote.take_dry_run_frames(), ["set brightness to Low"]);
+        assert!(remote.take_dry_run_frames().is_empty());
+        let mock = mock.lock().await;
+        assert!(matches!(*mock.brightness.lock().unwrap(), BrightnessLevel::High));
+        assert!(!mock.is_dry_run());
+    }
+
+    #[tokio::test]
+    async fn invalid_requests_get_error_codes() {
+        let (mock, client) = serve_mock(IQOSModel::Iluma);
+        mock.lock().await.fail_with("update_smartgesture", IQOSError::TimeoutError("smartgesture".to_string()));
+        let responses = exchange(client, &[
+            "not json",
+            r#"{"jsonrpc":"1.0","id":1,"method":"devices"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"fly","params":{"device":"MOCK0001"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"update_brightness","params":{"device":"MOCK0001"}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"update_brightness","params":{"device":"MOCK0001","level":"dim"}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"load_flexbattery","params":{"device":"MOCK0001"}}"#,
+            r#"{"jsonrpc":"2.0","id":6,"method":"update_smartgesture","params":{"device":"MOCK0001","enable":true}}"#,
+        ]).await;
+
+        assert_eq!(error_code(&responses[0]), Some(PARSE_ERROR));
+        assert!(responses[0].id.is_null());
+        assert_eq!(error_code(&responses[1]), Some(INVALID_REQUEST));
+        assert_eq!(error_code(&responses[2]), Some(METHOD_NOT_FOUND));
+        assert_eq!(error_code(&responses[3]), Some(INVALID_PARAMS));
+        assert_eq!(error_code(&responses[4]), Some(INVALID_PARAMS));
+        assert_eq!(error_code(&responses[5]), Some(UNSUPPORTED));
+        assert_eq!(error_code(&responses[6]), Some(TIMEOUT));
+        assert_eq!(responses[6].id, json!(6));
+    }
+
+    #[tokio::test]
+    async fn lists_connected_devices_without_naming_one() {
+        let (_, client) = serve_mock(IQOSModel::IlumaI);
+        let responses = exchange(client, &[
+            r#"{"jsonrpc":"2.0","id":1,"method":"devices"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"battery_status"}"#,
+        ]).await;
+
+        let devices = responses[0].result.as_ref().expect("devices");
+        assert_eq!(devices[0]["serial_number"], json!(SERIAL));
+        assert_eq!(responses[1].result, Some(json!(64)));
+    }
+}
@@ -0,0 +1,223 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::i18n::tr;
+use crate::loader::output;
+
+/// First line of history files written by rustyline, skipped when loading
+const RUSTYLINE_HEADER: &str = "#V2";
+
+/// Console command history, one command per line.
+///
+/// Each command is kept once: running it again moves it to the end. Only the
+/// newest `limit` commands are kept.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    limit: usize,
+    entries: Vec<String>,
+}
+
+impl History {
+    /// The default history file in `$XDG_STATE_HOME/iqos_cli/`, one per device
+    /// serial number if `serial` is given
+    pub fn default_path(serial: Option<&str>) -> PathBuf {
+        let file = match serial {
+            Some(serial) => format!("history-{}.txt", sanitize(serial)),
+            None => "history.txt".to_string(),
+        };
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("iqos_cli").join(&file))
+            .unwrap_or_else(|| PathBuf::from(file))
+    }
+
+    /// Load the history, starting empty if there is no file yet
+    pub fn load(path: PathBuf, limit: usize) -> Self {
+        let mut history = Self { path, limit, entries: Vec::new() };
+        if let Ok(content) = fs::read_to_string(&history.path) {
+            content.lines()
+                .filter(|line| *line != RUSTYLINE_HEADER)
+                .for_each(|line| history.add(line));
+        }
+        history
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Append `line`, removing an earlier copy of it
+    pub fn add(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| entry != line);
+        self.entries.push(line.to_string());
+        if self.entries.len() > self.limit {
+            let excess = self.entries.len() - self.limit;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Entries containing `pattern`, ignoring case, with their 1-based numbers
+    pub fn search<'a>(&'a self, pattern: &str) -> Vec<(usize, &'a str)> {
+        let pattern = pattern.to_lowercase();
+        self.entries.iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.to_lowercase().contains(&pattern))
+            .map(|(i, entry)| (i + 1, entry.as_str()))
+            .collect()
+    }
+
+    /// Print the numbered entries containing `pattern`, or every entry
+    pub fn print(&self, pattern: &str) {
+        let matches = self.search(pattern);
+        if matches.is_empty() {
+            output::message(tr!("No matching commands in the history"));
+            return;
+        }
+        let json: Vec<_> = matches.iter()
+            .map(|(number, command)| json!({ "number": number, "command": command }))
+            .collect();
+        let text: Vec<String> = matches.iter()
+            .map(|(number, command)| format!("{:>5}  {}", number, command))
+            .collect();
+        output::print(&json, text.join("\n"));
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut content = self.entries.join("\n");
+        content.push('\n');
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+/// Keep serial numbers usable as file names
+fn sanitize(serial: &str) -> String {
+    serial.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::config::Config;
+
+    /// A directory under the system temp dir, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("iqos_cli-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn history(lines: &[&str], limit: usize) -> History {
+        let mut history = History { path: PathBuf::from("unused"), limit, entries: Vec::new() };
+        lines.iter().for_each(|line| history.add(line));
+        history
+    }
+
+    #[test]
+    fn moves_repeated_commands_to_the_end() {
+        let history = history(&["battery", "brightness low", " battery "], 10);
+        assert_eq!(history.entries(), ["brightness low", "battery"]);
+    }
+
+    #[test]
+    fn skips_blank_lines() {
+        let history = history(&["", "   ", "info"], 10);
+        assert_eq!(history.entries(), ["info"]);
+    }
+
+    #[test]
+    fn keeps_the_newest_entries() {
+        let history = history(&["one", "two", "three", "four"], 2);
+        assert_eq!(history.entries(), ["three", "four"]);
+    }
+
+    #[test]
+    fn searches_ignoring_case_with_numbers() {
+        let history = history(&["brightness low", "battery", "Brightness high"], 10);
+        assert_eq!(history.search("BRIGHT"), [(1, "brightness low"), (3, "Brightness high")]);
+        assert_eq!(history.search("").len(), 3);
+        assert!(history.search("flexpuff").is_empty());
+    }
+
+    #[test]
+    fn saves_and_loads_without_the_rustyline_header() {
+        let dir = TempDir::new("history-roundtrip");
+        let path = dir.0.join("nested").join("history.txt");
+        let mut saved = history(&["battery", "lock"], 10);
+        saved.path = path.clone();
+        saved.save().unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, format!("{}\n{}", RUSTYLINE_HEADER, content)).unwrap();
+        let loaded = History::load(path, 10);
+        assert_eq!(loaded.entries(), ["battery", "lock"]);
+    }
+
+    #[test]
+    fn applies_the_limit_when_loading() {
+        let dir = TempDir::new("history-limit");
+        let path = dir.0.join("history.txt");
+        fs::write(&path, "one\ntwo\none\nthree\n").unwrap();
+        assert_eq!(History::load(path, 2).entries(), ["one", "three"]);
+    }
+
+    #[test]
+    fn missing_file_starts_empty() {
+        let dir = TempDir::new("history-missing");
+        assert!(History::load(dir.0.join("none.txt"), 10).entries().is_empty());
+    }
+
+    #[test]
+    fn default_file_per_device() {
+        assert_eq!(History::default_path(None).file_name().unwrap(), "history.txt");
+        assert_eq!(History::default_path(Some("AB:12/cd")).file_name().unwrap(), "history-AB_12_cd.txt");
+    }
+
+    #[test]
+    fn config_selects_the_history_file() {
+        let shared = Config { history_per_device: false, ..Config::default() };
+        assert_eq!(shared.history("SERIAL").path().file_name().unwrap(), "history.txt");
+
+        let per_device = Config { history_per_device: true, ..Config::default() };
+        assert_eq!(per_device.history("SERIAL").path().file_name().unwrap(), "history-SERIAL.txt");
+
+        let dir = TempDir::new("history-config");
+        let path = dir.0.join("mine.txt");
+        let explicit = Config { history: Some(path.clone()), history_per_device: true, history_size: Some(5), ..Config::default() };
+        let history = explicit.history("SERIAL");
+        assert_eq!(history.path(), path);
+        assert_eq!(history.limit(), 5);
+    }
+}
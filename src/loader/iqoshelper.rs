@@ -6,28 +6,61 @@ use rustyline::Context;
 use rustyline::Helper;
 use rustyline::error::ReadlineError;
 
+use console::style;
+
+use crate::loader::cmds::command::{Capabilities, CommandInfo};
+use crate::loader::prompt::SharedStatus;
+
 pub struct IqosHelper {
-    commands: Vec<String>,
+    commands: Vec<CommandInfo>,
+    capabilities: Capabilities,
     highlighter: MatchingBracketHighlighter,
     hinter: HistoryHinter,
+    status: Option<SharedStatus>,
 }
 
 impl IqosHelper {
-    pub fn new() -> Self {
-        let commands = vec![
-            "brightness".to_string(),
-            "findmyiqos".to_string(),
-            "smartgesture".to_string(),
-            "vibration".to_string(),
-            "help".to_string(),
-            "exit".to_string(),
-            "info".to_string(),
-        ];
-        
+    /// Complete the given commands, leaving out those the device does not support
+    pub fn new(commands: Vec<CommandInfo>, capabilities: Capabilities) -> Self {
+        let commands = commands.into_iter()
+            .filter(|info| info.is_supported(capabilities))
+            .collect();
+
         IqosHelper {
             commands,
+            capabilities,
             highlighter: MatchingBracketHighlighter::new(),
             hinter: HistoryHinter {},
+            status: None,
+        }
+    }
+
+    /// Show the prompt in red as soon as the device disconnects
+    pub fn with_status(mut self, status: SharedStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.status.as_ref()
+            .and_then(|status| status.lock().ok().map(|status| !status.is_connected()))
+            .unwrap_or(false)
+    }
+
+    fn command_names(&self) -> Vec<&'static str> {
+        self.commands.iter().map(|info| info.name).collect()
+    }
+
+    /// Candidates for the word after `words`
+    fn candidates(&self, words: &[&str]) -> Vec<&'static str> {
+        match words {
+            // The first word is the command name
+            [] => self.command_names(),
+            ["help"] => self.command_names(),
+            [command, args @ ..] => self.commands.iter()
+                .find(|info| info.name == command.to_lowercase())
+                .map(|info| info.complete(args, self.capabilities))
+                .unwrap_or_default(),
         }
     }
 }
@@ -41,81 +74,28 @@ impl Completer for IqosHelper {
         pos: usize,
         _ctx: &Context<'_>,
     ) -> Result<(usize, Vec<Self::Candidate>), ReadlineError> {
-        let args: Vec<&str> = line[..pos].split_whitespace().collect();
-        
-        if args.is_empty() {
-            // 入力がない場合は全コマンドを提案
-            let candidates: Vec<Pair> = self.commands
-                .iter()
-                .map(|cmd| Pair { display: cmd.clone(), replacement: cmd.clone() })
-                .collect();
-            
-            return Ok((0, candidates));
-        }
-        
-        if args.len() == 1 {
-            // 最初の引数の補完
-            let current = args[0];
-            let start = line.len() - current.len();
-            
-            let candidates: Vec<Pair> = self.commands
-                .iter()
-                .filter(|cmd| cmd.starts_with(current))
-                .map(|cmd| Pair { display: cmd.clone(), replacement: cmd.clone() })
-                .collect();
-            
-            return Ok((start, candidates));
-        }
-        
-        if args.len() == 2 {
-            // サブコマンドの補完
-            let cmd = args[0];
-            let subcmd = args[1];
-            let start = line.len() - subcmd.len();
-            
-            let candidates = match cmd {
-                "brightness" => vec!["high", "medium", "low"]
-                    .iter()
-                    .filter(|sc| sc.starts_with(subcmd))
-                    .map(|sc| Pair { display: sc.to_string(), replacement: sc.to_string() })
-                    .collect(),
-                    
-                "smartgesture" => vec!["enable", "disable"]
-                    .iter()
-                    .filter(|sc| sc.starts_with(subcmd))
-                    .map(|sc| Pair { display: sc.to_string(), replacement: sc.to_string() })
-                    .collect(),
-                
-                "vibration" => vec!["charge", "heating", "starting", "terminated", "puffend"]
-                    .iter()
-                    .filter(|sc| sc.starts_with(subcmd))
-                    .map(|sc| Pair { display: sc.to_string(), replacement: sc.to_string() })
-                    .collect(),
-                    
-                _ => vec![],
-            };
-            
-            return Ok((start, candidates));
-        }
-        
-        if args.len() == 3 && args[0] == "vibration" {
-            let option_value = args[2];
-            let start = line.len() - option_value.len();
-            
-            let candidates: Vec<Pair> = vec!["on", "off"]
-                .iter()
-                .filter(|val| val.starts_with(option_value))
-                .map(|val| Pair { display: val.to_string(), replacement: val.to_string() })
-                .collect();
-                
-            return Ok((start, candidates));
-        }
-        
-        Ok((pos, vec![]))
+        let line = &line[..pos];
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+
+        // Complete the word being typed
+        let current = if line.ends_with(char::is_whitespace) {
+            ""
+        } else {
+            words.pop().unwrap_or("")
+        };
+        let start = pos - current.len();
+
+        let candidates = self.candidates(&words)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(current))
+            .map(|candidate| Pair { display: candidate.to_string(), replacement: candidate.to_string() })
+            .collect();
+
+        Ok((start, candidates))
     }
 }
 
-// HelperトレイトおよびHelper関連トレイトの実装
+// Helper and its related traits
 impl Helper for IqosHelper {}
 
 impl Hinter for IqosHelper {
@@ -132,6 +112,9 @@ impl Highlighter for IqosHelper {
         prompt: &'p str,
         default: bool,
     ) -> std::borrow::Cow<'b, str> {
+        if self.is_disconnected() {
+            return std::borrow::Cow::Owned(style(prompt).red().to_string());
+        }
         self.highlighter.highlight_prompt(prompt, default)
     }
 
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use btleplug::platform::PeripheralId;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+use crate::iqos::{BuilderOptions, DiscoveredIqos, IQOSBuilder, IQOSModel, Iqos, IqosDevice, IqosScanner};
+
+const KNOWN_DEVICES_FILE: &str = "devices.toml";
+
+/// A device the CLI has connected to before.
+///
+/// The serial number identifies the device. The peripheral id is only a hint
+/// because some platforms rotate the BLE address.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownDevice {
+    pub serial: String,
+    pub peripheral_id: String,
+    pub model: IQOSModel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl KnownDevice {
+    /// The alias if one is set, otherwise the serial number
+    pub fn display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.serial)
+    }
+}
+
+/// Registry of known devices, stored in `devices.toml` in the user's config directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct KnownDevices {
+    /// Serial number of the device to connect to automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preferred: Option<String>,
+    #[serde(default, rename = "device")]
+    devices: Vec<KnownDevice>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl KnownDevices {
+    /// Load the registry, starting empty if there is no file yet
+    pub fn load() -> Self {
+        let path = dirs::config_dir().map(|dir| dir.join("iqos_cli").join(KNOWN_DEVICES_FILE));
+        let mut known: KnownDevices = path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| match toml::from_str(&content) {
+                Ok(known) => Some(known),
+                Err(e) => {
+                    eprintln!("{}", tr!("Ignoring invalid {}: {}", KNOWN_DEVICES_FILE, e));
+                    None
+                }
+            })
+            .unwrap_or_default();
+        known.path = path;
+        known
+    }
+
+    pub fn devices(&self) -> &[KnownDevice] {
+        &self.devices
+    }
+
+    /// Find a device by alias or serial number
+    pub fn find(&self, name: &str) -> Option<&KnownDevice> {
+        self.devices.iter().find(|d| d.alias.as_deref() == Some(name) || d.serial == name)
+    }
+
+    pub fn preferred(&self) -> Option<&KnownDevice> {
+        let serial = self.preferred.as_ref()?;
+        self.devices.iter().find(|d| d.serial == *serial)
+    }
+
+    pub fn is_preferred(&self, serial: &str) -> bool {
+        self.preferred.as_deref() == Some(serial)
+    }
+
+    pub fn contains(&self, id: &PeripheralId) -> bool {
+        let id = id.to_string();
+        self.devices.iter().any(|d| d.peripheral_id == id)
+    }
+
+    /// Record a connection, updating the peripheral id of an already known device
+    pub fn remember(&mut self, serial: &str, id: &PeripheralId, model: &IQOSModel) -> Result<()> {
+        let id = id.to_string();
+        match self.devices.iter_mut().find(|d| d.serial == serial) {
+            Some(device) => {
+                device.peripheral_id = id;
+                device.model = model.clone();
+            }
+            None => self.devices.push(KnownDevice {
+                serial: serial.to_string(),
+                peripheral_id: id,
+                model: model.clone(),
+                alias: None,
+            }),
+        }
+        self.save()
+    }
+
+    pub fn set_alias(&mut self, serial: &str, alias: Option<String>) -> Result<()> {
+        if let Some(alias) = &alias {
+            if self.devices.iter().any(|d| d.serial != serial && d.alias.as_ref() == Some(alias)) {
+                return Err(anyhow!(tr!("Alias '{}' is already used by another device", alias)));
+            }
+        }
+        let device = self.devices.iter_mut()
+            .find(|d| d.serial == serial)
+            .ok_or_else(|| anyhow!(tr!("Device {} is not registered", serial)))?;
+        device.alias = alias;
+        self.save()
+    }
+
+    pub fn set_preferred(&mut self, serial: Option<&str>) -> Result<()> {
+        self.preferred = serial.map(str::to_string);
+        self.save()
+    }
+
+    pub fn forget(&mut self, serial: &str) -> Result<()> {
+        self.devices.retain(|d| d.serial != serial);
+        if self.is_preferred(serial) {
+            self.preferred = None;
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Scan for a known device and connect to it without prompting.
+///
+/// A device advertising the stored peripheral id is used as soon as it shows
+/// up. If none does before `scan_timeout`, devices of the same model are
+/// tried one by one and the first with a matching serial number is kept.
+/// Each connection step may take up to `connect_timeout`.
+pub async fn connect_known(scanner: &IqosScanner, known: &KnownDevice, scan_timeout: Duration, connect_timeout: Duration) -> Result<(IqosDevice, PeripheralId)> {
+    let mut candidates: Vec<DiscoveredIqos> = Vec::new();
+    let mut devices = scanner.scan().await?.take_until(Box::pin(tokio::time::sleep(scan_timeout)));
+
+    while let Some(device) = devices.next().await {
+        if device.id.to_string() == known.peripheral_id {
+            scanner.stop().await?;
+            let iqos = connect(&device, known, connect_timeout).await?;
+            return Ok((iqos, device.id));
+        }
+        if device.guessed_model == known.model && !candidates.iter().any(|c| c.id == device.id) {
+            candidates.push(device);
+        }
+    }
+    scanner.stop().await?;
+
+    for device in candidates {
+        eprintln!("{}", tr!("Checking {} ({})...", device.name, device.id));
+        match connect(&device, known, connect_timeout).await {
+            Ok(iqos) => return Ok((iqos, device.id)),
+            Err(e) => eprintln!("  {}", e),
+        }
+    }
+
+    Err(anyhow!(tr!("{} was not found", known.display_name())))
+}
+
+async fn connect(device: &DiscoveredIqos, known: &KnownDevice, timeout: Duration) -> Result<IqosDevice> {
+    eprintln!("{}", tr!("Connecting to {} ({})...", known.display_name(), device.id));
+    let options = BuilderOptions::default()
+        .with_model(known.model.clone())
+        .with_timeout(timeout);
+    let mut iqos = IQOSBuilder::connect_and_build(device.peripheral().clone(), options).await?;
+
+    if iqos.serial_number() != known.serial {
+        let serial = iqos.serial_number().to_string();
+        let _ = iqos.disconnect().await;
+        return Err(anyhow!(tr!("serial number {} does not match", serial)));
+    }
+    Ok(iqos)
+}
@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::level_filters::LevelFilter;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+use crate::i18n::tr;
+
+/// Rotated log files kept next to the current one
+const KEPT_LOG_FILES: usize = 7;
+
+/// Level of this crate's events for the number of `-v` flags. Other crates
+/// such as btleplug only report warnings.
+fn level(verbosity: u8) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+fn targets(level: LevelFilter) -> Targets {
+    Targets::new()
+        .with_default(LevelFilter::WARN)
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+}
+
+/// Send log events to stderr and, with `log_file`, to a file that is rotated
+/// daily. The file gets at least debug events. Keep the returned guard alive
+/// until exit so buffered lines reach the file.
+pub fn init(verbosity: u8, log_file: Option<&Path>) -> Result<Option<WorkerGuard>> {
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(targets(level(verbosity)));
+
+    let Some(path) = log_file else {
+        tracing_subscriber::registry().with(stderr).init();
+        return Ok(None);
+    };
+
+    let directory = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name()
+        .with_context(|| tr!("Invalid log file: {}", path.display()))?
+        .to_string_lossy();
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(name)
+        .max_log_files(KEPT_LOG_FILES)
+        .build(directory)
+        .with_context(|| tr!("Could not open log file {}", path.display()))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let file = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_filter(targets(level(verbosity).max(LevelFilter::DEBUG)));
+
+    tracing_subscriber::registry().with(stderr).with(file).init();
+    Ok(Some(guard))
+}
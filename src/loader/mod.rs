@@ -1,6 +1,19 @@
 pub mod iqoshelper;
 pub mod parser;
 pub mod cmds;
+pub mod known_devices;
+pub mod config;
+pub mod picker;
+pub mod output;
+pub mod script;
+pub mod tui;
+pub mod completions;
+pub mod prompt;
+pub mod notify;
+pub mod logging;
+pub mod history;
+pub mod daemon;
 
 // Re-export essential components for ease of use
-pub use parser::{IQOSConsole, run_console};
+pub use parser::{IQOSConsole, run_console, run_once};
+pub use tui::run_tui;
@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use console::style;
+use rustyline::ExternalPrinter;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+
+use crate::i18n::tr;
+use crate::iqos::DeviceEvent;
+use crate::loader::output::clock_time;
+
+/// Which device events are printed above the console prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum NotifyLevel {
+    /// Nothing
+    Off,
+    /// Battery changes and disconnects
+    Normal,
+    /// Every frame the device sends, in hex
+    Verbose,
+}
+
+impl std::str::FromStr for NotifyLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(NotifyLevel::Off),
+            "normal" => Ok(NotifyLevel::Normal),
+            "verbose" => Ok(NotifyLevel::Verbose),
+            _ => Err(tr!("Unknown notification level: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for NotifyLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyLevel::Off => write!(f, "off"),
+            NotifyLevel::Normal => write!(f, "normal"),
+            NotifyLevel::Verbose => write!(f, "verbose"),
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(NotifyLevel::Normal as u8);
+
+pub fn level() -> NotifyLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        l if l == NotifyLevel::Off as u8 => NotifyLevel::Off,
+        l if l == NotifyLevel::Verbose as u8 => NotifyLevel::Verbose,
+        _ => NotifyLevel::Normal,
+    }
+}
+
+pub fn set_level(level: NotifyLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// How many lines are kept while a command runs; older ones are dropped
+const MAX_QUEUED: usize = 32;
+
+/// Print device events above the input line while the console waits for input
+/// (`idle` is true). Events that arrive while a command runs are queued and
+/// printed once it finishes, so they cannot mix with the command's output.
+pub fn follow<P>(mut printer: P, mut idle: watch::Receiver<bool>, mut events: broadcast::Receiver<DeviceEvent>)
+where
+    P: ExternalPrinter + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut battery = None;
+        let mut queued: VecDeque<String> = VecDeque::new();
+        let mut disconnected = false;
+        loop {
+            tokio::select! {
+                event = events.recv(), if !disconnected => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    };
+                    if let Some(message) = describe(&event, battery) {
+                        if queued.len() == MAX_QUEUED {
+                            queued.pop_front();
+                        }
+                        queued.push_back(format!("{} {}\n", style(clock_time()).dim(), message));
+                    }
+                    if let DeviceEvent::Battery { percent, .. } = event {
+                        battery = Some(percent);
+                    }
+                    disconnected = event == DeviceEvent::Disconnected;
+                },
+                changed = idle.changed() => if changed.is_err() {
+                    break;
+                },
+            }
+
+            if *idle.borrow() {
+                for line in queued.drain(..) {
+                    let _ = printer.print(line);
+                }
+            }
+            if disconnected && queued.is_empty() {
+                break;
+            }
+        }
+    });
+}
+
+/// The line to print for `event` at the current level, if any
+fn describe(event: &DeviceEvent, last_battery: Option<u8>) -> Option<String> {
+    let level = level();
+    if level == NotifyLevel::Off {
+        return None;
+    }
+    match event {
+        DeviceEvent::Battery { percent, .. } => match last_battery {
+            // The first reading is already shown in the prompt
+            None => None,
+            Some(last) if last == *percent => None,
+            Some(_) => Some(tr!("Battery {}%", percent)),
+        },
+        DeviceEvent::Disconnected => Some(style(tr!("Device disconnected")).red().bold().to_string()),
+        DeviceEvent::Notification { data } if level == NotifyLevel::Verbose => {
+            let hex: Vec<String> = data.iter().map(|b| format!("{:02x}", b)).collect();
+            Some(tr!("Notification: {}", hex.join(" ")))
+        },
+        DeviceEvent::Notification { .. } => None,
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::i18n::tr;
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+    /// One JSON document per result, for scripts
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(tr!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+static FORMAT: AtomicU8 = AtomicU8::new(OutputFormat::Text as u8);
+
+tokio::task_local! {
+    /// Format for one command's output, set by `with_format`
+    static COMMAND_FORMAT: OutputFormat;
+}
+
+/// The format of the running command, or the global one
+pub fn format() -> OutputFormat {
+    if let Ok(format) = COMMAND_FORMAT.try_with(|format| *format) {
+        return format;
+    }
+    if FORMAT.load(Ordering::Relaxed) == OutputFormat::Json as u8 {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    }
+}
+
+pub fn set_format(format: OutputFormat) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// Run `f` with a different output format. Only output printed by `f` is
+/// affected; notifications and other tasks keep the global format.
+pub async fn with_format<F: Future>(format: OutputFormat, f: F) -> F::Output {
+    COMMAND_FORMAT.scope(format, f).await
+}
+
+/// Print a command result: `text` in text mode, `value` in JSON mode
+pub fn print<T: Serialize + ?Sized>(value: &T, text: impl Display) {
+    match format() {
+        OutputFormat::Text => println!("{}", text),
+        OutputFormat::Json => match serde_json::to_string(value) {
+            Ok(json) => println!("{}", json),
+            Err(e) => println!("{}", json!({ "error": e.to_string() })),
+        },
+    }
+}
+
+/// Print a confirmation such as "Flexpuff enabled"
+pub fn message(text: impl Display) {
+    let text = text.to_string();
+    print(&json!({ "message": text }), &text);
+}
+
+static CONFIRM: AtomicBool = AtomicBool::new(true);
+
+pub fn set_confirm(enabled: bool) {
+    CONFIRM.store(enabled, Ordering::Relaxed);
+}
+
+/// Ask a yes/no `question` before a risky command. Without confirmations,
+/// or when stdin is not a terminal, the answer is yes.
+pub fn confirm(question: &str) -> bool {
+    if !CONFIRM.load(Ordering::Relaxed) || !std::io::stdin().is_terminal() {
+        return true;
+    }
+    print!("{}", tr!("{} [y/N] ", question));
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes" | "はい")
+}
+
+/// The time of day as `HH:MM:SS` (UTC), for timestamps in live output
+pub fn clock_time() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("{:02}:{:02}:{:02}", secs / 3600 % 24, secs / 60 % 60, secs % 60)
+}
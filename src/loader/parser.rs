@@ -4,34 +4,94 @@ use std::sync::Arc;
 use anyhow::Result;
 use rustyline::error::ReadlineError;
 use rustyline::{Config, Editor, DefaultEditor};
-use tokio::sync::Mutex;
+use serde_json::json;
+use tokio::sync::{watch, Mutex};
 
-use crate::iqos::IqosBle;
+use crate::i18n::{tr, translate};
+use crate::iqos::SharedIqos;
 use crate::iqos::device::Iqos;
-use crate::loader::cmds::command::{CommandFn, CommandRegistry};
+use crate::loader::cmds::command::{Capabilities, CommandError, CommandFn, CommandInfo, CommandRegistry, RegisteredCommand};
+use crate::loader::config;
+use crate::loader::history::History;
 use crate::loader::iqoshelper::IqosHelper;
+use crate::loader::output::{self, OutputFormat};
+use crate::loader::notify::{self, NotifyLevel};
+use crate::loader::prompt::{self, PromptStatus, SharedStatus};
+use crate::loader::script;
+
+/// Commands handled by the console loop itself rather than the registry
+const CONSOLE_COMMANDS: [CommandInfo; 3] = [
+    CommandInfo::new("source", "Run the commands in a script file", script::SOURCE_USAGE, false, false),
+    CommandInfo::new("history", "List previous commands, or those containing a text", "Usage: history [text]", false, false),
+    CommandInfo::new("exit", "Exit the program (also: quit)", "Usage: exit", false, false),
+];
+
+/// Commands registered by `register_builtin_commands`
+const BUILTIN_COMMANDS: [CommandInfo; 9] = [
+    CommandInfo::new("help", "Display available commands, or the usage of one", "Usage: help [command]", false, false),
+    CommandInfo::new("battery", "Display battery status", "Usage: battery", false, false),
+    CommandInfo::new("info", "Display device status", "Usage: info", false, false),
+    CommandInfo::new("lock", "Lock the device", "Usage: lock", false, false),
+    CommandInfo::new("unlock", "Unlock the device", "Usage: unlock", false, false),
+    CommandInfo::new("format", "Show or change the output format (or add --json to a command)", "Usage: format [text|json]", false, false)
+        .with_completer(complete_format),
+    CommandInfo::new("notify", "Show or change which device events are printed while idle", "Usage: notify [off|normal|verbose]", false, false)
+        .with_completer(complete_notify),
+    CommandInfo::new("dryrun", "Show frames instead of sending them to the device", "Usage: dryrun [on|off]", false, false)
+        .with_completer(complete_on_off),
+    CommandInfo::new("findmyiqos", "Vibrate the device until Enter is pressed", "Usage: findmyiqos", false, false),
+];
+
+fn builtin_info(name: &str) -> CommandInfo {
+    BUILTIN_COMMANDS.iter()
+        .find(|info| info.name == name)
+        .cloned()
+        .expect("builtin command info")
+}
+
+fn complete_format(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    if args.is_empty() { vec!["text", "json"] } else { vec![] }
+}
+
+fn complete_notify(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    if args.is_empty() { vec!["off", "normal", "verbose"] } else { vec![] }
+}
+
+fn complete_on_off(args: &[&str], _: Capabilities) -> Vec<&'static str> {
+    if args.is_empty() { vec!["on", "off"] } else { vec![] }
+}
 
 /// The main console handler for the IQOS CLI
 pub struct IQOSConsole {
     commands: Arc<Mutex<CommandRegistry>>,
-    pub iqos: Arc<Mutex<IqosBle>>,
+    pub iqos: SharedIqos,
 }
 
 impl IQOSConsole {
     /// Create a new console instance
-    pub fn new(iqos: IqosBle) -> Self {
+    pub fn new<D: Iqos + 'static>(iqos: D) -> Self {
+        Self::with_shared(Arc::new(Mutex::new(iqos)))
+    }
+
+    /// Create a console around a device that is already shared
+    pub fn with_shared(iqos: SharedIqos) -> Self {
         Self {
             commands: Arc::new(Mutex::new(HashMap::new())),
-            iqos: Arc::new(Mutex::new(iqos)),
+            iqos,
         }
     }
     
-    /// Register a new command
-    pub async fn register_command(&self, name: &str, command: CommandFn) {
+    /// Register a new command under the name in its info
+    pub async fn register_command(&self, info: CommandInfo, command: CommandFn) {
         let mut commands = self.commands.lock().await;
-        commands.insert(name.to_string(), command);
+        commands.insert(info.name.to_string(), RegisteredCommand::new(info, command));
     }
     
+    /// The registry, for commands that run other commands
+    pub(crate) fn registry(&self) -> Arc<Mutex<CommandRegistry>> {
+        self.commands.clone()
+    }
+
     /// Register multiple commands at once
     pub async fn register_commands<F>(&self, register_fn: F) 
     where
@@ -41,15 +101,29 @@ impl IQOSConsole {
         register_fn(&mut commands);
     }
     
-    /// Execute a command
-    pub async fn execute_command(&self, command: &str, args: Vec<String>) -> Result<()> {
-        let commands = self.commands.lock().await;
-        
-        if let Some(cmd) = commands.get(command) {
-            cmd(self.iqos.clone(), args).await
+    /// Execute a command. A `--json` argument prints this command's result as JSON.
+    pub async fn execute_command(&self, command: &str, mut args: Vec<String>) -> Result<()> {
+        let json = args.iter().position(|arg| arg == "--json").map(|i| args.remove(i)).is_some();
+
+        // Start the command, then release the registry so it can be read while running (e.g. by help)
+        let run = {
+            let commands = self.commands.lock().await;
+            let Some(cmd) = commands.get(command) else {
+                return Err(CommandError::UnknownCommand(command.to_string()).into());
+            };
+            (cmd.run)(self.iqos.clone(), args)
+        };
+        let iqos = self.iqos.clone();
+        let future = async move {
+            let result = run.await;
+            print_dry_run_frames(&iqos).await;
+            result
+        };
+
+        if json {
+            output::with_format(OutputFormat::Json, future).await
         } else {
-            println!("Unknown command: {}", command);
-            Ok(())
+            future.await
         }
     }
     
@@ -58,25 +132,53 @@ impl IQOSConsole {
         let commands = self.commands.lock().await;
         commands.keys().cloned().collect()
     }
+
+    /// Metadata of every command, registered or handled by the console, sorted by name
+    pub async fn command_infos(&self) -> Vec<CommandInfo> {
+        let commands = self.commands.lock().await;
+        all_command_infos(&commands)
+    }
     
-    /// Run the console interactive loop
-    pub async fn run(&self) -> Result<()> {
-        println!("IQOS Command Console v0.1.0");
-        println!("Type 'help' to display available commands, 'exit' to quit");
+    /// Run the console interactive loop, adding each command to `history`
+    pub async fn run(&self, mut history: History) -> Result<()> {
+        println!("{}", tr!("IQOS Command Console v{}", env!("CARGO_PKG_VERSION")));
+        println!("{}", tr!("Type 'help' to display available commands, 'exit' to quit"));
         
-        let config = Config::builder().build();
+        let config = Config::builder()
+            .max_history_size(history.limit())?
+            .history_ignore_dups(true)?
+            .build();
         let mut rl = Editor::<IqosHelper, rustyline::history::DefaultHistory>::with_config(config)?;
-        let helper = IqosHelper::new();
+        let (idle, idle_receiver) = watch::channel(false);
+        let (capabilities, status) = {
+            let iqos = self.iqos.lock().await;
+            let status: SharedStatus = Arc::new(std::sync::Mutex::new(PromptStatus::of(&*iqos)));
+            if let Some(source) = iqos.event_source() {
+                let events = source.spawn();
+                prompt::follow(status.clone(), events.resubscribe());
+                if let Ok(printer) = rl.create_external_printer() {
+                    notify::follow(printer, idle_receiver, events);
+                }
+            }
+            (Capabilities::of(&*iqos), status)
+        };
+        let helper = IqosHelper::new(self.command_infos().await, capabilities)
+            .with_status(status.clone());
         rl.set_helper(Some(helper));
         
-        if rl.load_history("history.txt").is_err() {
-            println!("No history file found");
+        for entry in history.entries() {
+            let _ = rl.add_history_entry(entry.as_str());
         }
         
         loop {
-            match rl.readline("iqos> ") {
+            let prompt = status.lock().map(|status| status.render()).unwrap_or_else(|_| "iqos> ".to_string());
+            idle.send_replace(true);
+            let line = rl.readline(&prompt);
+            idle.send_replace(false);
+            match line {
                 Ok(line_str) => {
                     let _ = rl.add_history_entry(&line_str);
+                    history.add(&line_str);
                     
                     let args: Vec<String> = line_str.trim()
                         .split_whitespace()
@@ -93,12 +195,26 @@ impl IQOSConsole {
                         let mut iqos = self.iqos.lock().await;
                         // Use the Iqos trait method
                         let _ = Iqos::disconnect(&mut *iqos).await;
-                        println!("Goodbye!");
+                        println!("{}", tr!("Goodbye!"));
                         break;
                     }
                     
-                    if let Err(e) = self.execute_command(&cmd, args).await {
-                        println!("Command execution error: {}", e);
+                    let result = if cmd == "source" {
+                        script::source(self, &args).await
+                    } else if cmd == "history" {
+                        history.print(&args[1..].join(" "));
+                        Ok(())
+                    } else {
+                        self.execute_command(&cmd, args).await
+                    };
+                    if let Err(e) = result {
+                        report_error(&e);
+                    }
+
+                    // Commands such as battery read the level; show it without waiting for an event
+                    let battery = self.iqos.lock().await.battery_status();
+                    if let Ok(mut status) = status.lock() {
+                        status.update_battery(battery);
                     }
                 },
                 Err(ReadlineError::Interrupted) => {
@@ -110,30 +226,68 @@ impl IQOSConsole {
                     break;
                 },
                 Err(err) => {
-                    println!("Error: {:?}", err);
+                    println!("{}", tr!("Error: {}", format!("{:?}", err)));
                     break;
                 }
             }
         }
         
-        rl.save_history("history.txt")?;
+        if let Err(e) = history.save() {
+            println!("{}", tr!("Could not save history to {}: {}", history.path().display(), e));
+        }
         
         Ok(())
     }
 }
 
+/// Print a command error with its causes. Usage and model errors are shown
+/// as they are, anything else is reported as a failed command.
+pub fn report_error(e: &anyhow::Error) {
+    if e.downcast_ref::<CommandError>().is_some() {
+        println!("{:#}", e);
+    } else {
+        println!("{}", tr!("Command execution error: {}", format!("{:#}", e)));
+    }
+}
+
+/// Show the frames a dry run kept from the device, also after a failure
+async fn print_dry_run_frames(iqos: &SharedIqos) {
+    let frames = iqos.lock().await.take_dry_run_frames();
+    if !frames.is_empty() {
+        output::print(&json!({ "dry_run_frames": frames }), frames.join("\n"));
+    }
+}
+
 /// Run the console application
-pub async fn run_console(iqos: IqosBle) -> Result<()> {
+pub async fn run_console<D: Iqos + 'static>(iqos: D, config: &config::Config, history: History) -> Result<()> {
     let console = IQOSConsole::new(iqos);
     
     // Register all commands
-    register_all_commands(&console).await;
+    register_all_commands(&console, config).await;
     
-    console.run().await
+    console.run(history).await
+}
+
+/// Run a single command against the device, then disconnect.
+/// `run <file>` runs a script instead.
+pub async fn run_once<D: Iqos + 'static>(iqos: D, config: &config::Config, args: Vec<String>) -> Result<()> {
+    let console = IQOSConsole::new(iqos);
+    register_all_commands(&console, config).await;
+
+    let cmd = args.first().map(|s| s.to_lowercase()).unwrap_or_default();
+    let result = if cmd == "run" || cmd == "source" {
+        script::source(&console, &args).await
+    } else {
+        console.execute_command(&cmd, args).await
+    };
+
+    let mut iqos = console.iqos.lock().await;
+    let _ = Iqos::disconnect(&mut *iqos).await;
+    result
 }
 
-/// Register all available commands
-async fn register_all_commands(console: &IQOSConsole) {
+/// Register all available commands, and the aliases from `config`
+async fn register_all_commands(console: &IQOSConsole, config: &config::Config) {
     // Register built-in commands first
     register_builtin_commands(console).await;
     
@@ -144,105 +298,143 @@ async fn register_all_commands(console: &IQOSConsole) {
     crate::loader::cmds::vibration::register_command(console).await;
     crate::loader::cmds::autostart::register_command(console).await;
     crate::loader::cmds::smartgesture::register_command(console).await;
-    
-    // TODO: Register other command modules here as needed
+    crate::loader::cmds::device::register_command(console).await;
+    crate::loader::cmds::watch::register_command(console).await;
+
+    // User aliases last, so they cannot shadow the commands above
+    crate::loader::cmds::alias::register_aliases(console, &config.aliases).await;
 }
 
 /// Register built-in simple commands
 async fn register_builtin_commands(console: &IQOSConsole) {
     // Register help command
-    console.register_command("help", Box::new(|iqos, _| {
-        Box::pin(async move {
-            let iqos = iqos.lock().await;
-            
-            println!("Available commands:");
-            
-            // Common commands
-            println!("  battery - Display battery status");
-            println!("  lock | unlock - Lock or unlock the device");
-            println!("  findmyiqos - Activate find my device feature");
-            println!("  autostart [on|off] - Configure auto-start feature");
-            
-            // ILUMA model specific commands
-            if iqos.is_iluma_or_higher() {
-                println!("\nILUMA model specific commands:");
-                println!("  brightness [high|low] - Set brightness");
-                println!("  smartgesture [enable|disable] - Configure Smart Gesture feature");
-                println!("  flexpuff [enable|disable] - Configure FlexPuff feature");
-                println!("  vibration [option on|off]... - Configure vibration settings");
-                println!("    Example: vibration charge on heating on puffend on");
-                println!("    Options: charge, heating, starting, terminated, puffend");
-            }
-            
-            println!("\nOther commands:");
-            println!("  info - Display device status");
-            println!("  help - Display this help message");
-            println!("  quit | exit - Exit the program");
-            
-            Ok(())
-        })
-    })).await;
+    let commands = console.commands.clone();
+    console.register_command(
+        builtin_info("help"),
+        Box::new(move |iqos, args| {
+            let commands = commands.clone();
+            Box::pin(async move {
+                let commands = commands.lock().await;
+                let iqos = iqos.lock().await;
+                print_help(&all_command_infos(&commands), &*iqos, args.get(1).map(|s| s.as_str()))
+            })
+        }),
+    ).await;
     
     // Register battery command
-    console.register_command("battery", Box::new(|iqos, _| {
+    console.register_command(builtin_info("battery"), Box::new(|iqos, _| {
         Box::pin(async move {
             let mut iqos = iqos.lock().await;
             // Use the Iqos trait methods explicitly
             Iqos::reload_battery(&mut *iqos).await?;
-            println!("Battery status: {}%", Iqos::battery_status(&*iqos));
+            let battery = Iqos::battery_status(&*iqos);
+            output::print(&json!({ "battery": battery }), tr!("Battery status: {}%", battery));
             Ok(())
         })
     })).await;
 
     // Register info command
-    console.register_command("info", Box::new(|iqos, _| {
+    console.register_command(builtin_info("info"), Box::new(|iqos, _| {
         Box::pin(async move {
             let iqos = iqos.lock().await;
-            println!("\n{}\n", iqos);
+            output::print(&iqos.device_info(), format!("\n{}\n", iqos));
             Ok(())
         })
     })).await;
     
     // Register lock command
-    console.register_command("lock", Box::new(|iqos, _| {
+    console.register_command(builtin_info("lock"), Box::new(|iqos, _| {
         Box::pin(async move {
+            if !output::confirm(tr!("Lock the IQOS? It cannot be used until it is unlocked")) {
+                output::message(tr!("Cancelled"));
+                return Ok(());
+            }
             let iqos = iqos.lock().await;
             // Use the Iqos trait method explicitly
             Iqos::lock_device(&*iqos).await?;
-            println!("Locked the IQOS");
+            output::message(tr!("Locked the IQOS"));
             Ok(())
         })
     })).await;
 
     // Register unlock command
-    console.register_command("unlock", Box::new(|iqos, _| {
+    console.register_command(builtin_info("unlock"), Box::new(|iqos, _| {
         Box::pin(async move {
             let iqos = iqos.lock().await;
             // Use the Iqos trait method explicitly
             Iqos::unlock_device(&*iqos).await?;
-            println!("Unlocked the IQOS");
+            output::message(tr!("Unlocked the IQOS"));
             Ok(())
         })
     })).await;
     
+    // Register format command
+    console.register_command(builtin_info("format"), Box::new(|_, args| {
+        Box::pin(async move {
+            match args.get(1) {
+                Some(format) => {
+                    let format = format.parse::<OutputFormat>()
+                        .map_err(|_| CommandError::Usage(tr!("Usage: format [text|json]").to_string()))?;
+                    output::set_format(format);
+                    output::message(tr!("Output format set to {}", format));
+                },
+                None => output::print(&json!({ "format": output::format().to_string() }), tr!("Output format: {}", output::format())),
+            }
+            Ok(())
+        })
+    })).await;
+
+    // Register notify command
+    console.register_command(builtin_info("notify"), Box::new(|_, args| {
+        Box::pin(async move {
+            match args.get(1) {
+                Some(level) => {
+                    let level = level.parse::<NotifyLevel>()
+                        .map_err(|_| CommandError::Usage(tr!("Usage: notify [off|normal|verbose]").to_string()))?;
+                    notify::set_level(level);
+                    output::message(tr!("Notifications set to {}", level));
+                },
+                None => output::print(&json!({ "notify": notify::level().to_string() }), tr!("Notifications: {}", notify::level())),
+            }
+            Ok(())
+        })
+    })).await;
+
+    // Register dryrun command
+    console.register_command(builtin_info("dryrun"), Box::new(|iqos, args| {
+        Box::pin(async move {
+            let iqos = iqos.lock().await;
+            match args.get(1).map(|arg| arg.as_str()) {
+                Some("on") => iqos.set_dry_run(true),
+                Some("off") => iqos.set_dry_run(false),
+                Some(_) => return Err(CommandError::Usage(tr!("Usage: dryrun [on|off]").to_string()).into()),
+                None => {},
+            }
+            let enabled = iqos.is_dry_run();
+            let text = if enabled { tr!("Dry run is on: frames are shown, not sent") } else { tr!("Dry run is off") };
+            output::print(&json!({ "dry_run": enabled }), text);
+            Ok(())
+        })
+    })).await;
+
     // Register findmyiqos command
-    console.register_command("findmyiqos", Box::new(|iqos, _| {
+    console.register_command(builtin_info("findmyiqos"), Box::new(|iqos, _| {
         let mut rl = DefaultEditor::new().unwrap();
         Box::pin(async move {
             let iqos = iqos.lock().await;
-            println!("Find My IQOS...");
+            println!("{}", tr!("Find My IQOS..."));
             // Use the Iqos trait method explicitly
             Iqos::vibrate(&*iqos).await?;
-            let input = rl.readline("Press <Enter> to stop vibration");
+            let input = rl.readline(tr!("Press <Enter> to stop vibration"));
             
             match input {
                 Ok(_) => {
                     // Use the Iqos trait method explicitly
                     Iqos::stop_vibrate(&*iqos).await?;
-                    print!("Vibration stopped.\n");
+                    println!("{}", tr!("Vibration stopped."));
                 }
                 Err(_) => {
-                    println!("Vibration stopped.");
+                    println!("{}", tr!("Vibration stopped."));
                 }
             }
 
@@ -250,3 +442,57 @@ async fn register_builtin_commands(console: &IQOSConsole) {
         })
     })).await;
 }
+
+/// Metadata of the builtin, module and console commands, without a device.
+/// User aliases are not included.
+pub fn known_command_infos() -> Vec<CommandInfo> {
+    use crate::loader::cmds::{autostart, brightness, device, flexbattery, flexpuff, smartgesture, vibration, watch};
+
+    let mut infos: Vec<CommandInfo> = BUILTIN_COMMANDS.into_iter()
+        .chain([
+            flexpuff::command_info(),
+            flexbattery::command_info(),
+            brightness::command_info(),
+            vibration::command_info(),
+            autostart::command_info(),
+            smartgesture::command_info(),
+            device::command_info(),
+            watch::command_info(),
+        ])
+        .chain(CONSOLE_COMMANDS)
+        .collect();
+    infos.sort_by_key(|info| info.name);
+    infos
+}
+
+fn all_command_infos(commands: &CommandRegistry) -> Vec<CommandInfo> {
+    let mut infos: Vec<CommandInfo> = commands.values()
+        .map(|command| command.info.clone())
+        .chain(CONSOLE_COMMANDS)
+        .collect();
+    infos.sort_by_key(|info| info.name);
+    infos
+}
+
+/// Print the commands the device supports, or the usage of `topic`
+fn print_help(infos: &[CommandInfo], iqos: &dyn Iqos, topic: Option<&str>) -> Result<()> {
+    if let Some(topic) = topic {
+        let info = infos.iter()
+            .find(|info| info.name == topic.to_lowercase())
+            .ok_or_else(|| CommandError::UnknownCommand(topic.to_string()))?;
+        println!("{} - {}", info.name, translate(info.description));
+        println!("{}", translate(info.usage));
+        if let Some(model) = info.required_model() {
+            let note = if info.is_supported_by(iqos) { "" } else { tr!(" (not supported by this device)") };
+            println!("{}{}", tr!("Requires an {} model", model), note);
+        }
+        return Ok(());
+    }
+
+    println!("{}", tr!("Available commands for {}:", iqos.model()));
+    for info in infos.iter().filter(|info| info.is_supported_by(iqos)) {
+        println!("  {:<14}{}", info.name, translate(info.description));
+    }
+    println!("\n{}", tr!("Type 'help <command>' for usage, or add --json to a command for JSON output"));
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use std::io::{stdout, Write};
+
+use anyhow::Result;
+use crossterm::cursor::MoveToPreviousLine;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Stylize;
+use crossterm::terminal::{self, disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use crossterm::queue;
+use futures::StreamExt;
+
+use crate::i18n::tr;
+use crate::iqos::{DiscoveredIqos, IqosScanner};
+use crate::loader::known_devices::KnownDevices;
+
+/// Show a live list of IQOS devices in range and let the user pick one.
+///
+/// Devices are sorted by signal strength. A known device is preselected until
+/// the user moves the selection. Returns `None` if the user cancels.
+pub async fn pick_device(scanner: &IqosScanner, known: &KnownDevices) -> Result<Option<DiscoveredIqos>> {
+    let mut devices = scanner.scan().await?;
+    let mut keys = EventStream::new();
+    let mut picker = Picker::new(known);
+
+    let raw_mode = RawMode::enable()?;
+    picker.draw()?;
+
+    let picked = loop {
+        tokio::select! {
+            Some(device) = devices.next() => {
+                picker.insert(device);
+                picker.draw()?;
+            }
+            event = keys.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    match picker.handle_key(key) {
+                        Action::Redraw => picker.draw()?,
+                        Action::Pick => break picker.take_selected(),
+                        Action::Cancel => break None,
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => break None,
+            }
+        }
+    };
+
+    drop(raw_mode);
+    scanner.stop().await?;
+    Ok(picked)
+}
+
+enum Action {
+    Redraw,
+    Pick,
+    Cancel,
+}
+
+struct Picker<'a> {
+    devices: Vec<DiscoveredIqos>,
+    selected: usize,
+    moved_by_user: bool,
+    number: String,
+    known: &'a KnownDevices,
+    drawn_lines: u16,
+}
+
+impl<'a> Picker<'a> {
+    fn new(known: &'a KnownDevices) -> Self {
+        Self {
+            devices: Vec::new(),
+            selected: 0,
+            moved_by_user: false,
+            number: String::new(),
+            known,
+            drawn_lines: 0,
+        }
+    }
+
+    /// Add a device, or refresh its name and signal strength if it is listed
+    fn insert(&mut self, device: DiscoveredIqos) {
+        let selected_id = self.devices.get(self.selected).map(|d| d.id.clone());
+        let prefer_new = !self.moved_by_user
+            && self.known.contains(&device.id)
+            && !selected_id.as_ref().is_some_and(|id| self.known.contains(id));
+        let new_id = device.id.clone();
+
+        self.devices.retain(|d| d.id != new_id);
+        self.devices.push(device);
+        self.devices.sort_by(|a, b| b.rssi.cmp(&a.rssi));
+
+        let keep = if prefer_new { Some(new_id) } else { selected_id };
+        self.selected = keep
+            .and_then(|id| self.devices.iter().position(|d| d.id == id))
+            .unwrap_or(0);
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Action::Cancel,
+            KeyCode::Esc | KeyCode::Char('q') => return Action::Cancel,
+            KeyCode::Enter if !self.devices.is_empty() => return Action::Pick,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                self.moved_by_user = true;
+                self.number.clear();
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.selected + 1 < self.devices.len() {
+                    self.selected += 1;
+                }
+                self.moved_by_user = true;
+                self.number.clear();
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                // Keep accumulating digits while they form a valid entry number,
+                // otherwise start over from this digit
+                self.number.push(c);
+                if !self.select_number() {
+                    self.number = c.to_string();
+                    self.select_number();
+                }
+            }
+            KeyCode::Backspace => {
+                self.number.pop();
+            }
+            _ => {}
+        }
+        Action::Redraw
+    }
+
+    fn select_number(&mut self) -> bool {
+        match self.number.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= self.devices.len() => {
+                self.selected = n - 1;
+                self.moved_by_user = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn take_selected(&mut self) -> Option<DiscoveredIqos> {
+        if self.selected < self.devices.len() {
+            Some(self.devices.swap_remove(self.selected))
+        } else {
+            None
+        }
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        let mut out = stdout();
+
+        if self.drawn_lines > 0 {
+            queue!(out, MoveToPreviousLine(self.drawn_lines))?;
+        }
+        queue!(out, Clear(ClearType::FromCursorDown))?;
+
+        let mut lines = vec![
+            tr!("Scanning for IQOS devices (↑/↓ or number to select, Enter to connect, q to quit)").to_string(),
+        ];
+        if self.devices.is_empty() {
+            lines.push(format!("  {}", tr!("Waiting for devices...")));
+        }
+        for (i, device) in self.devices.iter().enumerate() {
+            let rssi = device.rssi.map_or("   ? dBm".to_string(), |rssi| format!("{:>4} dBm", rssi));
+            let known = if self.known.contains(&device.id) { tr!(" (known)") } else { "" };
+            let marker = if i == self.selected { ">" } else { " " };
+            let line = format!(
+                "{} {:>2}. {:<8} {:<24} {} {}{}",
+                marker, i + 1, device.guessed_model.to_string(), device.name, rssi, device.id, known,
+            );
+            lines.push(line);
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            let line: String = line.chars().take(width.saturating_sub(1)).collect();
+            if i > 0 && i - 1 == self.selected && !self.devices.is_empty() {
+                write!(out, "{}\r\n", line.reverse())?;
+            } else {
+                write!(out, "{}\r\n", line)?;
+            }
+        }
+        out.flush()?;
+
+        self.drawn_lines = lines.len() as u16;
+        Ok(())
+    }
+}
+
+/// Keeps the terminal in raw mode while the picker is shown
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        enable_raw_mode()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
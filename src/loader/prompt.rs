@@ -0,0 +1,89 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::i18n::tr;
+use crate::iqos::device::Iqos;
+use crate::iqos::DeviceEvent;
+use crate::loader::known_devices::KnownDevices;
+
+/// What the console prompt shows about the device, e.g. `ILUMA i [82%] iqos> `
+#[derive(Debug, Clone)]
+pub struct PromptStatus {
+    model: String,
+    alias: Option<String>,
+    battery: Option<u8>,
+    connected: bool,
+}
+
+/// Prompt status shared with the task that follows device events
+pub type SharedStatus = Arc<Mutex<PromptStatus>>;
+
+impl PromptStatus {
+    pub fn of(iqos: &dyn Iqos) -> Self {
+        let alias = KnownDevices::load().devices().iter()
+            .find(|device| device.serial == iqos.serial_number())
+            .and_then(|device| device.alias.clone());
+        let mut status = Self {
+            model: iqos.model().to_string(),
+            alias,
+            battery: None,
+            connected: true,
+        };
+        status.update_battery(iqos.battery_status());
+        status
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Take the battery level read by a command. 0 means not read yet.
+    pub fn update_battery(&mut self, percent: u8) {
+        if percent > 0 {
+            self.battery = Some(percent);
+        }
+    }
+
+    pub fn apply(&mut self, event: &DeviceEvent) {
+        match event {
+            DeviceEvent::Battery { percent, .. } => self.battery = Some(*percent),
+            DeviceEvent::Disconnected => self.connected = false,
+            DeviceEvent::Notification { .. } => {},
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut prompt = self.model.clone();
+        if let Some(alias) = &self.alias {
+            prompt.push_str(&format!(" ({})", alias));
+        }
+        if !self.connected {
+            prompt.push_str(&format!(" [{}]", tr!("disconnected")));
+        } else if let Some(battery) = self.battery {
+            prompt.push_str(&format!(" [{}%]", battery));
+        }
+        prompt.push_str(" iqos> ");
+        prompt
+    }
+}
+
+/// Keep `status` up to date from device events until the device disconnects
+pub fn follow(status: SharedStatus, mut events: broadcast::Receiver<DeviceEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Ok(mut status) = status.lock() {
+                        status.apply(&event);
+                    }
+                    if event == DeviceEvent::Disconnected {
+                        break;
+                    }
+                },
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
@@ -0,0 +1,249 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::i18n::{tr, translate};
+use crate::iqos::device::Iqos;
+use crate::loader::cmds::command::CommandError;
+use crate::loader::parser::{report_error, IQOSConsole};
+
+pub const SOURCE_USAGE: &str = "Usage: source <file> [--continue-on-error]";
+
+/// What to do when a line of a script fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorMode {
+    Stop,
+    Continue,
+}
+
+/// Run a script of console commands, one per line.
+///
+/// Besides the console commands, a script may contain `# comments`,
+/// `sleep <duration>` (e.g. `500ms`, `2s`, `1m`) and assertions such as
+/// `expect battery >= 50`.
+pub async fn run_script(console: &IQOSConsole, path: &Path, mode: ErrorMode) -> Result<()> {
+    let script = fs::read_to_string(path)
+        .with_context(|| tr!("Could not read {}", path.display()))?;
+    let mut failed = 0;
+
+    for (number, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let args: Vec<String> = line.split_whitespace().map(|s| s.to_string()).collect();
+        if args.is_empty() {
+            continue;
+        }
+
+        let location = format!("{}:{}", path.display(), number + 1);
+        if let Err(e) = run_line(console, args).await {
+            let e = e.context(location);
+            match mode {
+                ErrorMode::Stop => return Err(e),
+                ErrorMode::Continue => {
+                    report_error(&e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!(tr!("{} line(s) of {} failed", failed, path.display())));
+    }
+    Ok(())
+}
+
+/// Parse the arguments of `source`/`run` and run the script
+pub async fn source(console: &IQOSConsole, args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut mode = ErrorMode::Stop;
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--continue-on-error" => mode = ErrorMode::Continue,
+            "--stop-on-error" => mode = ErrorMode::Stop,
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(CommandError::Usage(translate(SOURCE_USAGE).to_string()).into()),
+        }
+    }
+    let path = path.ok_or_else(|| CommandError::Usage(translate(SOURCE_USAGE).to_string()))?;
+    run_script(console, Path::new(path), mode).await
+}
+
+async fn run_line(console: &IQOSConsole, args: Vec<String>) -> Result<()> {
+    let cmd = args[0].to_lowercase();
+    match cmd.as_str() {
+        "sleep" => {
+            let duration = args.get(1)
+                .and_then(|arg| parse_duration(arg))
+                .ok_or_else(|| CommandError::Usage(tr!("Usage: sleep <duration>, e.g. sleep 2s").to_string()))?;
+            tokio::time::sleep(duration).await;
+            Ok(())
+        },
+        "expect" => expect(console, &args[1..]).await,
+        "source" | "run" => Err(CommandError::Usage(tr!("Scripts cannot source other scripts").to_string()).into()),
+        _ => console.execute_command(&cmd, args).await,
+    }
+}
+
+/// Parse `500ms`, `2s`, `1m` or a plain number of seconds
+pub(crate) fn parse_duration(arg: &str) -> Option<Duration> {
+    let split = arg.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(arg.len());
+    let (value, unit) = arg.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let seconds = match unit {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        _ => return None,
+    };
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Check `battery <op> <percent>` or `model == <model>`
+async fn expect(console: &IQOSConsole, args: &[String]) -> Result<()> {
+    let usage = || CommandError::Usage(tr!("Usage: expect battery <op> <percent> | expect model == <model>").to_string());
+    let [subject, op, expected @ ..] = args else {
+        return Err(usage().into());
+    };
+    let expected = expected.join(" ");
+
+    let (actual, holds) = match subject.as_str() {
+        "battery" => {
+            let expected: u8 = expected.parse().map_err(|_| usage())?;
+            let mut iqos = console.iqos.lock().await;
+            Iqos::reload_battery(&mut *iqos).await?;
+            let battery = Iqos::battery_status(&*iqos);
+            let holds = match op.as_str() {
+                ">=" => battery >= expected,
+                "<=" => battery <= expected,
+                ">" => battery > expected,
+                "<" => battery < expected,
+                "==" => battery == expected,
+                "!=" => battery != expected,
+                _ => return Err(usage().into()),
+            };
+            (format!("{}%", battery), holds)
+        },
+        "model" => {
+            let model = console.iqos.lock().await.model().to_string();
+            let holds = match op.as_str() {
+                "==" => model.eq_ignore_ascii_case(&expected),
+                "!=" => !model.eq_ignore_ascii_case(&expected),
+                _ => return Err(usage().into()),
+            };
+            (model, holds)
+        },
+        _ => return Err(usage().into()),
+    };
+
+    if !holds {
+        return Err(anyhow!(tr!("Expectation failed: {} {} {} ({} is {})", subject, op, expected, subject, actual)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iqos::mock::MockIqos;
+    use crate::iqos::IQOSModel;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn is_usage(error: &anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<CommandError>(), Some(CommandError::Usage(_)))
+    }
+
+    #[test]
+    fn parses_each_unit() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2s"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1m"), Some(Duration::from_secs(60)));
+        assert_eq!(parse_duration("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn parses_zero() {
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("0ms"), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("fast"), None);
+        assert_eq!(parse_duration("5h"), None);
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("1.2.3s"), None);
+    }
+
+    #[tokio::test]
+    async fn expect_compares_battery() {
+        let console = IQOSConsole::new(MockIqos::new(IQOSModel::Iluma, "SERIAL", 60));
+        assert!(expect(&console, &args("battery >= 60")).await.is_ok());
+        assert!(expect(&console, &args("battery != 0")).await.is_ok());
+        assert!(expect(&console, &args("battery < 60")).await.is_err());
+        assert!(expect(&console, &args("battery > 0")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn expect_compares_model_ignoring_case() {
+        let console = IQOSConsole::new(MockIqos::new(IQOSModel::IlumaI, "SERIAL", 60));
+        assert!(expect(&console, &args("model == iluma i")).await.is_ok());
+        assert!(expect(&console, &args("model != ILUMA")).await.is_ok());
+        let error = expect(&console, &args("model == ONE")).await.unwrap_err();
+        assert!(!is_usage(&error));
+    }
+
+    #[tokio::test]
+    async fn expect_rejects_malformed_assertions() {
+        let console = IQOSConsole::new(MockIqos::new(IQOSModel::Iluma, "SERIAL", 60));
+        for line in ["battery", "battery >= high", "battery ~ 50", "model > ONE", "charge == 1"] {
+            let error = expect(&console, &args(line)).await.unwrap_err();
+            assert!(is_usage(&error), "{}", line);
+        }
+    }
+
+    /// A script file in the temp dir, removed when dropped
+    struct ScriptFile(std::path::PathBuf);
+
+    impl ScriptFile {
+        fn new(name: &str, content: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("iqos_cli-{}-{}.iqos", name, std::process::id()));
+            fs::write(&path, content).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for ScriptFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_lines_keep_their_cause() {
+        let console = IQOSConsole::new(MockIqos::new(IQOSModel::Iluma, "SERIAL", 60));
+        let script = ScriptFile::new("cause", "# setup\nexpect battery >= 90\n");
+        let error = run_script(&console, &script.0, ErrorMode::Stop).await.unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(message.starts_with(&format!("{}:2: ", script.0.display())), "{}", message);
+        assert!(message.contains("Expectation failed: battery >= 90 (battery is 60%)"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn failed_lines_keep_their_error_kind() {
+        let console = IQOSConsole::new(MockIqos::new(IQOSModel::Iluma, "SERIAL", 60));
+        let script = ScriptFile::new("kind", "sleep soon\n");
+        let error = run_script(&console, &script.0, ErrorMode::Stop).await.unwrap_err();
+
+        assert!(is_usage(&error));
+        assert!(format!("{:#}", error).contains("Usage: sleep <duration>"));
+    }
+}
@@ -0,0 +1,524 @@
+use std::io::{stdout, Stdout};
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use futures::StreamExt;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::i18n::{tr, translate};
+use crate::iqos::brightness::BrightnessLevel;
+use crate::iqos::device::{DeviceInfo, Iqos};
+use crate::iqos::error::IQOSError;
+use crate::iqos::events::EventSource;
+use crate::iqos::flexbattery::{FlexBattery, FlexbatteryMode};
+use crate::iqos::vibration::IlumaVibration;
+use crate::iqos::{DeviceEvent, Flexpuff, SharedIqos, VibrationSettings};
+use crate::loader::cmds::command::Capabilities;
+use crate::loader::output::clock_time;
+
+const BATTERY_REFRESH: Duration = Duration::from_secs(30);
+const LOG_LINES: usize = 100;
+
+/// Full-screen dashboard: device info, battery, and settings that can be
+/// changed with the keyboard, with a log of device events and changes.
+pub async fn run_tui(iqos: SharedIqos) -> Result<()> {
+    let mut dashboard = Dashboard::new(&iqos).await;
+    dashboard.reload_all(&iqos).await;
+    let mut events = iqos.lock().await.event_source().map(EventSource::spawn);
+
+    let mut terminal = TerminalGuard::enter()?;
+    let mut keys = EventStream::new();
+    // Only for devices without events, which report battery changes themselves
+    let mut battery_timer = tokio::time::interval(BATTERY_REFRESH);
+
+    loop {
+        terminal.draw(|frame| dashboard.draw(frame))?;
+
+        tokio::select! {
+            event = keys.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Up | KeyCode::Char('k') => dashboard.select_previous(),
+                    KeyCode::Down | KeyCode::Char('j') => dashboard.select_next(),
+                    KeyCode::Enter | KeyCode::Char(' ') => dashboard.toggle_selected(&iqos).await,
+                    KeyCode::Char('r') => dashboard.reload_all(&iqos).await,
+                    _ => {}
+                },
+                Some(Ok(Event::Resize(_, _))) => terminal.clear()?,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            },
+            event = next_event(&mut events), if events.is_some() => match event {
+                Some(event) => dashboard.on_event(event),
+                None => events = None,
+            },
+            _ = battery_timer.tick(), if events.is_none() => dashboard.reload_battery(&iqos).await,
+        }
+    }
+
+    drop(terminal);
+    let mut iqos = iqos.lock().await;
+    let _ = Iqos::disconnect(&mut *iqos).await;
+    Ok(())
+}
+
+/// A row of the settings panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    Brightness,
+    VibrationCharge,
+    VibrationHeating,
+    VibrationStarting,
+    VibrationPuffEnd,
+    VibrationTerminated,
+    FlexPuff,
+    FlexBatteryMode,
+    PauseMode,
+    AutoStart,
+    SmartGesture,
+}
+
+impl Setting {
+    const ALL: [Setting; 11] = [
+        Setting::Brightness,
+        Setting::VibrationCharge,
+        Setting::VibrationHeating,
+        Setting::VibrationStarting,
+        Setting::VibrationPuffEnd,
+        Setting::VibrationTerminated,
+        Setting::FlexPuff,
+        Setting::FlexBatteryMode,
+        Setting::PauseMode,
+        Setting::AutoStart,
+        Setting::SmartGesture,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Setting::Brightness => "Brightness",
+            Setting::VibrationCharge => "Vibrate when charge starts",
+            Setting::VibrationHeating => "Vibrate when heating starts",
+            Setting::VibrationStarting => "Vibrate when ready to use",
+            Setting::VibrationPuffEnd => "Vibrate when puffs end soon",
+            Setting::VibrationTerminated => "Vibrate when terminated",
+            Setting::FlexPuff => "FlexPuff",
+            Setting::FlexBatteryMode => "FlexBattery",
+            Setting::PauseMode => "Pause mode",
+            Setting::AutoStart => "Autostart",
+            Setting::SmartGesture => "Smart Gesture",
+        }
+    }
+
+    fn is_available(&self, capabilities: Capabilities) -> bool {
+        match self {
+            Setting::VibrationHeating
+            | Setting::VibrationStarting
+            | Setting::VibrationPuffEnd
+            | Setting::VibrationTerminated => true,
+            Setting::FlexBatteryMode | Setting::PauseMode => capabilities.iluma_i,
+            _ => capabilities.iluma,
+        }
+    }
+}
+
+/// Last known values. `None` means not loaded yet; autostart and smart
+/// gesture cannot be read back, so they are only known once set here.
+#[derive(Default)]
+struct Settings {
+    brightness: Option<BrightnessLevel>,
+    vibration: Option<VibrationSettings>,
+    flexpuff: Option<Flexpuff>,
+    flexbattery: Option<FlexBattery>,
+    autostart: Option<bool>,
+    smartgesture: Option<bool>,
+}
+
+/// The next device event, or `None` once the device stops reporting
+async fn next_event(events: &mut Option<broadcast::Receiver<DeviceEvent>>) -> Option<DeviceEvent> {
+    let receiver = events.as_mut()?;
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return Some(event),
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+struct Dashboard {
+    info: DeviceInfo,
+    charger_battery: Option<u8>,
+    capabilities: Capabilities,
+    settings: Settings,
+    rows: Vec<Setting>,
+    selected: ListState,
+    log: Vec<String>,
+}
+
+impl Dashboard {
+    async fn new(iqos: &SharedIqos) -> Self {
+        let iqos = iqos.lock().await;
+        let capabilities = Capabilities::of(&*iqos);
+        let rows: Vec<Setting> = Setting::ALL.into_iter().filter(|s| s.is_available(capabilities)).collect();
+        Self {
+            info: iqos.device_info(),
+            charger_battery: iqos.charger_battery_status(),
+            capabilities,
+            settings: Settings::default(),
+            rows,
+            selected: ListState::default().with_selected(Some(0)),
+            log: Vec::new(),
+        }
+    }
+
+    fn log(&mut self, message: impl Into<String>) {
+        let time = clock_time();
+        self.log.push(format!("{} {}", time, message.into()));
+        if self.log.len() > LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+
+    fn select_previous(&mut self) {
+        let i = self.selected.selected().unwrap_or(0);
+        self.selected.select(Some(i.saturating_sub(1)));
+    }
+
+    fn select_next(&mut self) {
+        let i = self.selected.selected().unwrap_or(0);
+        if i + 1 < self.rows.len() {
+            self.selected.select(Some(i + 1));
+        }
+    }
+
+    async fn reload_battery(&mut self, iqos: &SharedIqos) {
+        let mut iqos = iqos.lock().await;
+        match Iqos::reload_battery(&mut *iqos).await {
+            Ok(()) => self.update_battery(Iqos::battery_status(&*iqos), iqos.charger_battery_status()),
+            Err(e) => self.log(format!("{}: {}", tr!("Battery"), e)),
+        }
+    }
+
+    fn update_battery(&mut self, holder: u8, charger: Option<u8>) {
+        if holder != self.info.battery {
+            self.log(tr!("Battery {}% -> {}%", self.info.battery, holder));
+            self.info.battery = holder;
+        }
+        if let (Some(before), Some(now)) = (self.charger_battery, charger) {
+            if before != now {
+                self.log(tr!("Charger battery {}% -> {}%", before, now));
+            }
+        }
+        self.charger_battery = charger.or(self.charger_battery);
+    }
+
+    fn on_event(&mut self, event: DeviceEvent) {
+        match event {
+            DeviceEvent::Battery { percent, charger } => self.update_battery(percent, charger),
+            DeviceEvent::Disconnected => self.log(tr!("Device disconnected")),
+            // Replies to the dashboard's own requests
+            DeviceEvent::Notification { .. } => {},
+        }
+    }
+
+    /// Vibration toggles write every option, so they wait for the current values
+    fn is_editable(&self, setting: Setting) -> bool {
+        self.settings.vibration.is_some() || !matches!(
+            setting,
+            Setting::VibrationCharge
+                | Setting::VibrationHeating
+                | Setting::VibrationStarting
+                | Setting::VibrationPuffEnd
+                | Setting::VibrationTerminated
+        )
+    }
+
+    async fn reload_all(&mut self, iqos: &SharedIqos) {
+        self.reload_battery(iqos).await;
+        let iqos = iqos.lock().await;
+
+        if let Some(iluma) = iqos.as_iluma() {
+            match iqos.load_brightness().await {
+                Ok(level) => self.settings.brightness = Some(level),
+                Err(e) => self.log(format!("{}: {}", translate(Setting::Brightness.label()), e)),
+            }
+            match iluma.load_iluma_vibration_settings().await {
+                Ok(vibration) => self.settings.vibration = Some(vibration),
+                Err(e) => self.log(format!("{}: {}", tr!("Vibration"), e)),
+            }
+            match iluma.load_flexpuff().await {
+                Ok(flexpuff) => self.settings.flexpuff = Some(flexpuff),
+                Err(e) => self.log(format!("{}: {}", translate(Setting::FlexPuff.label()), e)),
+            }
+        } else {
+            match iqos.load_vibration_settings().await {
+                Ok(vibration) => self.settings.vibration = Some(vibration),
+                Err(e) => self.log(format!("{}: {}", tr!("Vibration"), e)),
+            }
+        }
+        if let Some(iluma_i) = iqos.as_iluma_i() {
+            match iluma_i.load_flexbattery().await {
+                Ok(flexbattery) => self.settings.flexbattery = Some(flexbattery),
+                Err(e) => self.log(format!("{}: {}", translate(Setting::FlexBatteryMode.label()), e)),
+            }
+        }
+        self.log_dry_run_frames(&*iqos);
+        self.log(tr!("Settings loaded"));
+    }
+
+    async fn toggle_selected(&mut self, iqos: &SharedIqos) {
+        let Some(setting) = self.selected.selected().and_then(|i| self.rows.get(i).copied()) else {
+            return;
+        };
+        if !self.is_editable(setting) {
+            self.log(tr!("Vibration settings could not be loaded; press r to reload them"));
+            return;
+        }
+        let iqos = iqos.lock().await;
+        let result = self.toggle(&*iqos, setting).await;
+        self.log_dry_run_frames(&*iqos);
+        match result {
+            Ok(value) => self.log(tr!("{} set to {}", translate(setting.label()), value)),
+            Err(e) => self.log(format!("{}: {}", translate(setting.label()), e)),
+        }
+    }
+
+    /// In a dry run, the frames that were not sent
+    fn log_dry_run_frames(&mut self, iqos: &dyn Iqos) {
+        for frame in iqos.take_dry_run_frames() {
+            self.log(frame);
+        }
+    }
+
+    /// Flip a setting on the device and return its new value for the log
+    async fn toggle(&mut self, iqos: &dyn Iqos, setting: Setting) -> Result<String> {
+        let settings = &mut self.settings;
+        match setting {
+            Setting::Brightness => {
+                let level = match settings.brightness {
+                    Some(BrightnessLevel::High) => BrightnessLevel::Low,
+                    _ => BrightnessLevel::High,
+                };
+                iqos.update_brightness(level).await?;
+                settings.brightness = Some(level);
+                Ok(brightness_name(level).to_string())
+            }
+            Setting::FlexPuff => {
+                let iluma = iqos.as_iluma().ok_or(IQOSError::IncompatibleModelError)?;
+                let enabled = !settings.flexpuff.is_some_and(|f| f.is_enabled());
+                iluma.update_flexpuff(Flexpuff::new(enabled)).await?;
+                settings.flexpuff = Some(Flexpuff::new(enabled));
+                Ok(on_off(enabled).to_string())
+            }
+            Setting::FlexBatteryMode | Setting::PauseMode => {
+                let iluma_i = iqos.as_iluma_i().ok_or(IQOSError::IncompatibleModelError)?;
+                let current = settings.flexbattery.as_ref();
+                let pause = current.and_then(|f| f.is_pausemode()).unwrap_or(false);
+                let mut new = match (setting, current.map(|f| f.is_performance())) {
+                    (Setting::FlexBatteryMode, Some(true)) => FlexBattery::new(FlexbatteryMode::Eco),
+                    (Setting::FlexBatteryMode, _) => FlexBattery::new(FlexbatteryMode::Performance),
+                    (_, Some(false)) => return Err(anyhow::anyhow!(tr!("Pause mode is only available in performance mode"))),
+                    _ => FlexBattery::new(FlexbatteryMode::Performance),
+                };
+                if setting == Setting::PauseMode {
+                    new.update_pause_mode(!pause);
+                }
+                iluma_i.update_flexbattery(new).await?;
+                // Reload to pick up the pause mode that goes with the new mode
+                let loaded = iluma_i.load_flexbattery().await?;
+                let value = if setting == Setting::PauseMode {
+                    on_off(loaded.is_pausemode().unwrap_or(false)).to_string()
+                } else {
+                    loaded.mode().to_string()
+                };
+                settings.flexbattery = Some(loaded);
+                Ok(value)
+            }
+            Setting::AutoStart | Setting::SmartGesture => {
+                let iluma = iqos.as_iluma().ok_or(IQOSError::IncompatibleModelError)?;
+                let slot = if setting == Setting::AutoStart { &mut settings.autostart } else { &mut settings.smartgesture };
+                let enable = !slot.unwrap_or(false);
+                if setting == Setting::AutoStart {
+                    iluma.update_autostart(enable).await?;
+                } else {
+                    iluma.update_smartgesture(enable).await?;
+                }
+                *slot = Some(enable);
+                Ok(on_off(enable).to_string())
+            }
+            _ => {
+                let mut vibration = settings.vibration.clone()
+                    .ok_or_else(|| anyhow::anyhow!(tr!("Vibration settings could not be loaded; press r to reload them")))?;
+                let enable = !vibration_value(&vibration, setting);
+                match setting {
+                    Setting::VibrationCharge => vibration.iluma_and_higher = Some(IlumaVibration::new(enable)),
+                    Setting::VibrationHeating => vibration.when_heating_start = Some(enable),
+                    Setting::VibrationStarting => vibration.when_starting_to_use = Some(enable),
+                    Setting::VibrationPuffEnd => vibration.when_puff_end = Some(enable),
+                    _ => vibration.when_manually_terminated = Some(enable),
+                }
+                match iqos.as_iluma() {
+                    Some(iluma) => iluma.update_iluma_vibration_settings(vibration.clone()).await?,
+                    None => iqos.update_vibration_settings(vibration.clone()).await?,
+                }
+                settings.vibration = Some(vibration);
+                Ok(on_off(enable).to_string())
+            }
+        }
+    }
+
+    fn value(&self, setting: Setting) -> String {
+        let settings = &self.settings;
+        let value = match setting {
+            Setting::Brightness => settings.brightness.map(|level| brightness_name(level).to_string()),
+            Setting::FlexPuff => settings.flexpuff.map(|f| on_off(f.is_enabled()).to_string()),
+            Setting::FlexBatteryMode => settings.flexbattery.as_ref().map(|f| f.mode().to_string()),
+            Setting::PauseMode => settings.flexbattery.as_ref().map(|f| match f.is_pausemode() {
+                Some(pause) => on_off(pause).to_string(),
+                None => tr!("n/a (eco)").to_string(),
+            }),
+            Setting::AutoStart => settings.autostart.map(|on| on_off(on).to_string()),
+            Setting::SmartGesture => settings.smartgesture.map(|on| on_off(on).to_string()),
+            _ => settings.vibration.as_ref().map(|v| on_off(vibration_value(v, setting)).to_string()),
+        };
+        value.unwrap_or_else(|| "?".to_string())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, middle, bottom, footer] = Layout::vertical([
+            Constraint::Length(9),
+            Constraint::Min(self.rows.len() as u16 + 2),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+        let [info_area, battery_area] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(top);
+
+        let info = &self.info;
+        let mut lines = vec![
+            field(tr!("Model"), &info.model),
+            field(tr!("Serial number"), &info.serial_number),
+            field(tr!("Model number"), &info.model_number),
+            field(tr!("Product number"), &info.product_number),
+            field(tr!("Software"), &info.software_revision),
+        ];
+        if let Some(holder) = &info.holder_product_number {
+            lines.push(field(tr!("Holder product"), holder));
+        }
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!(" {} ", tr!("Device")))), info_area);
+
+        let [holder_area, charger_area] = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(battery_area);
+        frame.render_widget(battery_gauge(tr!("Holder battery"), Some(info.battery)), holder_area);
+        frame.render_widget(battery_gauge(tr!("Charger battery"), self.charger_battery), charger_area);
+
+        let items: Vec<ListItem> = self.rows.iter()
+            .map(|&setting| {
+                let item = ListItem::new(format!("{:<30} {}", translate(setting.label()), self.value(setting)));
+                if self.is_editable(setting) { item } else { item.style(Style::default().fg(Color::DarkGray)) }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!(" {} ", tr!("Settings"))))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, middle, &mut self.selected);
+
+        let visible = bottom.height.saturating_sub(2) as usize;
+        let log: Vec<Line> = self.log.iter().rev().take(visible).rev().map(|l| Line::from(l.as_str())).collect();
+        frame.render_widget(Paragraph::new(log).block(Block::default().borders(Borders::ALL).title(format!(" {} ", tr!("Events")))), bottom);
+
+        let model_note = if self.capabilities.iluma { "" } else { tr!("  (ILUMA settings hidden for this model)") };
+        frame.render_widget(
+            Paragraph::new(format!("{}{}", tr!("↑/↓ select  Enter/Space toggle  r reload  q quit"), model_note)),
+            footer,
+        );
+    }
+}
+
+/// A battery level gauge; without a level it is empty and says so
+fn battery_gauge(title: &str, percent: Option<u8>) -> Gauge<'static> {
+    let color = match percent {
+        Some(0..=20) => Color::Red,
+        Some(21..=50) => Color::Yellow,
+        Some(_) => Color::Green,
+        None => Color::DarkGray,
+    };
+    let gauge = Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title(format!(" {} ", title)))
+        .gauge_style(Style::default().fg(color))
+        .percent(percent.unwrap_or(0).min(100) as u16);
+    match percent {
+        Some(_) => gauge,
+        None => gauge.label(tr!("not reported")),
+    }
+}
+
+fn vibration_value(vibration: &VibrationSettings, setting: Setting) -> bool {
+    match setting {
+        Setting::VibrationCharge => vibration.when_charging_start(),
+        Setting::VibrationHeating => vibration.when_heating_start(),
+        Setting::VibrationStarting => vibration.when_starting_to_use(),
+        Setting::VibrationPuffEnd => vibration.when_puff_end(),
+        _ => vibration.when_manually_terminated(),
+    }
+}
+
+fn brightness_name(level: BrightnessLevel) -> &'static str {
+    match level {
+        BrightnessLevel::High => tr!("high"),
+        BrightnessLevel::Low => tr!("low"),
+    }
+}
+
+fn field(label: &str, value: impl std::fmt::Display) -> Line<'static> {
+    Line::from(format!("{:<15} {}", format!("{}:", label), value))
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { tr!("on") } else { tr!("off") }
+}
+
+/// Raw mode and the alternate screen for as long as the dashboard is shown
+struct TerminalGuard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+        Ok(Self { terminal })
+    }
+}
+
+impl std::ops::Deref for TerminalGuard {
+    type Target = Terminal<CrosstermBackend<Stdout>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.terminal
+    }
+}
+
+impl std::ops::DerefMut for TerminalGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.terminal
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+    }
+}
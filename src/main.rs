@@ -1,90 +1,260 @@
-use std::io::Write;
-use btleplug::api::{
-    Central, CentralEvent, Manager as _, Peripheral as _, ScanFilter,
-};
-use btleplug::platform::{Adapter, Manager, PeripheralId};
-use futures::stream::StreamExt;
-use std::error::Error;
+use anyhow::Result;
+use btleplug::platform::{Manager, PeripheralId};
+use clap::{CommandFactory, Parser};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::net::UnixStream;
+use tokio::sync::Mutex;
 
+mod i18n;
 mod iqos;
 mod loader;
 
-use loader::run_console;
-use iqos::{Iqos, IqosIluma, IqosBle};
+use i18n::{tr, Lang};
+use iqos::{adapter, Iqos, IqosDevice};
+use loader::{completions, daemon, logging, run_console, run_once, run_tui};
+use loader::cmds::command::{exit_code, CommandError};
+use loader::config::Config;
+use loader::daemon::RemoteIqos;
+use loader::known_devices::{connect_known, KnownDevices};
+use loader::output::{self, OutputFormat};
+use loader::picker::pick_device;
 
-async fn get_central(manager: &Manager) -> Adapter {
-    let adapters = manager.adapters().await.unwrap();
-    adapters.into_iter().nth(0).unwrap()
+/// Command line interface for IQOS devices
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about,
+    after_help = "Without a command the interactive console is started.\n\
+                  Defaults are read from ~/.config/iqos_cli/config.toml; flags override them.\n\
+                  While `iqos_cli daemon` runs, other invocations use its connection.\n\
+                  `iqos_cli serve --http 127.0.0.1:8080` serves the devices over HTTP as JSON.\n\
+                  Exit status: 0 success, 1 command failed, 2 invalid usage,\n\
+                  3 not supported by this model, 4 device not found or Bluetooth error"
+)]
+struct Cli {
+    /// Connect to a known device by alias or serial number
+    #[arg(long, value_name = "NAME")]
+    device: Option<String>,
+
+    /// Bluetooth adapter to use, by name (hci1) or index
+    #[arg(long)]
+    adapter: Option<String>,
+
+    /// List the Bluetooth adapters and exit
+    #[arg(long)]
+    list_adapters: bool,
+
+    /// Output format for command results [default: text]
+    #[arg(long, value_enum)]
+    output: Option<OutputFormat>,
+
+    /// Language for messages; defaults to LANG
+    #[arg(long, value_enum)]
+    lang: Option<Lang>,
+
+    /// Re-run the one-shot command at this interval (e.g. 30s), highlighting changes
+    #[arg(long, value_name = "INTERVAL")]
+    watch: Option<String>,
+
+    /// Print the frames commands would send instead of sending them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Log what the device library does: -v for operations, -vv also for frame hex
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Also write logs to this file, rotated daily (the last 7 files are kept)
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Seconds to scan for a known device before giving up [default: 10]
+    #[arg(long, value_name = "SECS")]
+    scan_timeout: Option<u64>,
+
+    /// Seconds allowed for each step of connecting to a device [default: 10]
+    #[arg(long, value_name = "SECS")]
+    connect_timeout: Option<u64>,
+
+    /// Console history file [default: $XDG_STATE_HOME/iqos_cli/history.txt]
+    #[arg(long, value_name = "PATH")]
+    history: Option<PathBuf>,
+
+    /// Do not ask before locking or forgetting a device
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Unix socket of the daemon [default: $XDG_RUNTIME_DIR/iqos_cli/daemon.sock]
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    /// Connect to the device directly even if a daemon is running
+    #[arg(long)]
+    no_daemon: bool,
+
+    /// Run a single console command and exit, e.g. `brightness high`,
+    /// `run <file>` to run a script of commands, `tui` for the dashboard,
+    /// `daemon [device...]` to keep devices connected for other invocations,
+    /// `serve --http <ADDR> [device...]` to control devices over HTTP,
+    /// or `completions <bash|zsh|fish>` to print a shell completion script
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let mut iqos_builder: iqos::IQOSBuilder;
-    let manager = Manager::new().await.unwrap();
-
-    // get the first bluetooth adapter
-    let central = get_central(&manager).await;
-
-    let central_state = central.adapter_state().await.unwrap();
-    println!("CentralState: {:?}", central_state);
-
-    let mut events = central.events().await?;
-    let mut ignore_devices: Vec<PeripheralId> = vec![];
-    // start scanning for devices
-    central.start_scan(ScanFilter::default()).await?;
-
-    while let Some(event) = events.next().await {
-        match event {
-            CentralEvent::DeviceDiscovered(addr) => {
-                let peripheral = central.peripheral(&addr).await?;
-                let properties = peripheral.properties().await?;
-                let name = properties
-                    .and_then(|p| p.local_name)
-                    .map(|local_name| local_name.to_string())
-                    .unwrap_or_default();
-
-                if name.contains("IQOS") && !ignore_devices.contains(&addr) {
-                    iqos_builder = iqos::IQOSBuilder::new(peripheral);
-
-                    println!("Found IQOS: {name} ({addr})");
-                    
-                    loop {
-                        print!("Connect to {name} ({addr})? [y/N]: ");
-                        let _  = std::io::stdout().flush();
-                        let mut input = String::new();
-                        std::io::stdin().read_line(&mut input)?;
-
-                        if input.trim().to_lowercase() == "y" {
-                            println!("Connecting to IQOS device...");
-                            iqos_builder.connect().await?;
-                            println!("Connected!");
-                            let _services = iqos_builder.discover_services().await?;
-                            iqos_builder.initialize().await?;
-
-                            let iqos = iqos_builder.build().await?;
-                            central.stop_scan().await?;
-                            run_console(iqos).await?;
-                            return Ok(());
-                        } else if input.trim().to_lowercase() == "n" {
-                            ignore_devices.push(addr.clone());
-                            println!("Scanning for other devices...");
-                            break;
-                        }
-                        continue
-                    }
-                }
-            }
-            CentralEvent::StateUpdate(state) => {
-                println!("State Update: {:?}", state);
-            }
-            CentralEvent::DeviceConnected(id) => {
-                println!("Device Connected: {id}");
-            }
-            CentralEvent::DeviceDisconnected(id) => {
-                println!("Device Disconnected: {id}");
-            }
-            _ => {}
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    i18n::set_lang(cli.lang.unwrap_or_else(Lang::from_env));
+    let config = merge(&cli, Config::load());
+    i18n::set_lang(config.language.unwrap_or_else(Lang::from_env));
+    let _log_guard = match logging::init(cli.verbose, cli.log_file.as_deref()) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return ExitCode::from(exit_code(&e));
+        }
+    };
+
+    match run(cli, config).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // With the causes, e.g. the failed script line and why it failed
+            eprintln!("{:#}", e);
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// Settings from config.toml, with the flags given on the command line in their place
+fn merge(cli: &Cli, mut config: Config) -> Config {
+    config.adapter = cli.adapter.clone().or(config.adapter);
+    config.device = cli.device.clone().or(config.device);
+    config.output = cli.output.or(config.output);
+    config.language = cli.lang.or(config.language);
+    config.history = cli.history.clone().or(config.history);
+    config.socket = cli.socket.clone().or(config.socket);
+    config.confirm = config.confirm && !cli.yes;
+    config.timeouts.scan = cli.scan_timeout.unwrap_or(config.timeouts.scan);
+    config.timeouts.connect = cli.connect_timeout.unwrap_or(config.timeouts.connect);
+    config
+}
+
+async fn run(cli: Cli, config: Config) -> Result<()> {
+    output::set_format(config.output.unwrap_or(OutputFormat::Text));
+    output::set_confirm(config.confirm);
+    if cli.command.first().map(String::as_str) == Some("completions") {
+        return completions::print(Cli::command(), cli.command.get(1).map(String::as_str));
+    }
+
+    // A running daemon already holds the connection
+    let socket = config.socket_path();
+    let is_daemon = matches!(cli.command.first().map(String::as_str), Some("daemon" | "serve"));
+    if !is_daemon && !cli.no_daemon && !cli.list_adapters {
+        if let Ok(stream) = UnixStream::connect(&socket).await {
+            let iqos = RemoteIqos::connect(stream, config.device.as_deref()).await?;
+            return start(cli, config, iqos).await;
+        }
+    }
+
+    let manager = Manager::new().await?;
+    let one_shot = !cli.command.is_empty();
+
+    if cli.list_adapters {
+        for (i, entry) in adapter::list_adapters(&manager).await?.iter().enumerate() {
+            println!("{}: {} [{:?}]", i, entry.info, entry.state);
         }
+        return Ok(());
     }
-    Ok(())
-}
\ No newline at end of file
+
+    let central = adapter::select_adapter(&manager, config.adapter.as_deref()).await?;
+
+    if !adapter::is_powered_on(&central).await? {
+        eprintln!("{}", tr!("Bluetooth adapter is powered off. Waiting for it to be turned on (Ctrl-C to quit)..."));
+        adapter::wait_until_powered_on(&central).await?;
+        eprintln!("{}", tr!("Bluetooth adapter is on"));
+    }
+
+    let mut known = KnownDevices::load();
+    let scanner = iqos::IqosScanner::new(central);
+
+    match cli.command.first().map(String::as_str) {
+        Some("daemon") => return daemon::serve(scanner, &config, &socket, &cli.command[1..]).await,
+        Some("serve") => return daemon::http::serve(scanner, &config, &cli.command[1..]).await,
+        _ => {},
+    }
+
+    let Some((iqos, id)) = connect(&config, &scanner, &known, one_shot).await? else {
+        eprintln!("{}", tr!("No device selected"));
+        return Ok(());
+    };
+
+    if let Err(e) = known.remember(iqos.serial_number(), &id, iqos.model()) {
+        eprintln!("{}", tr!("Could not save known devices: {}", e));
+    }
+
+    start(cli, config, iqos).await
+}
+
+/// Run the dashboard, a one-shot command or the console on a connected device
+async fn start<D: Iqos + 'static>(cli: Cli, config: Config, iqos: D) -> Result<()> {
+    let one_shot = !cli.command.is_empty();
+    if cli.dry_run {
+        iqos.set_dry_run(true);
+    }
+
+    if cli.command.len() == 1 && cli.command[0] == "tui" {
+        run_tui(Arc::new(Mutex::new(iqos))).await
+    } else if let (true, Some(interval)) = (one_shot, cli.watch) {
+        let command = ["watch".to_string(), interval].into_iter().chain(cli.command).collect();
+        run_once(iqos, &config, command).await
+    } else if one_shot {
+        run_once(iqos, &config, cli.command).await
+    } else {
+        let history = config.history(iqos.serial_number());
+        run_console(iqos, &config, history).await
+    }
+}
+
+/// Connect to the requested or preferred device, or let the user pick one
+async fn connect(config: &Config, scanner: &iqos::IqosScanner, known: &KnownDevices, one_shot: bool) -> Result<Option<(IqosDevice, PeripheralId)>> {
+    let (scan_timeout, connect_timeout) = (config.timeouts.scan(), config.timeouts.connect());
+
+    if let Some(name) = &config.device {
+        let target = known.find(name)
+            .ok_or_else(|| CommandError::Connection(tr!("Unknown device '{}'. Use 'device list' in the console to see known devices", name)))?;
+        let connected = connect_known(scanner, target, scan_timeout, connect_timeout).await
+            .map_err(|e| CommandError::Connection(e.to_string()))?;
+        return Ok(Some(connected));
+    }
+
+    if let Some(preferred) = known.preferred() {
+        match connect_known(scanner, preferred, scan_timeout, connect_timeout).await {
+            Ok(connected) => return Ok(Some(connected)),
+            Err(e) if one_shot => return Err(CommandError::Connection(e.to_string()).into()),
+            Err(e) => eprintln!("{}", tr!("Could not connect to the preferred device: {}", e)),
+        }
+    }
+
+    if one_shot && !std::io::stdin().is_terminal() {
+        return Err(CommandError::Connection(tr!("No device specified. Use --device or set a preferred device with 'device prefer'").to_string()).into());
+    }
+
+    let Some(device) = pick_device(scanner, known).await? else {
+        return Ok(None);
+    };
+
+    eprintln!("{}", tr!("Connecting to {} ({})...", device.name, device.id));
+    let options = iqos::BuilderOptions::default().with_timeout(connect_timeout);
+    let iqos_builder = iqos::IQOSBuilder::with_options(device.peripheral().clone(), options)
+        .connect().await?;
+    eprintln!("{}", tr!("Connected!"));
+
+    let iqos = iqos_builder
+        .discover_services().await?
+        .initialize().await?
+        .build().await?;
+    Ok(Some((iqos, device.id)))
+}